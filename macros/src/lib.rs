@@ -0,0 +1,135 @@
+#![warn(missing_docs)]
+//! Function-like macros that expand a state definition into a Bevy State Plugin,
+//! without a `build.rs` or a generated file.
+//!
+//! ```rust ignore
+//! use bevy_state_plugin_generator_macros::bevy_states;
+//! bevy_states! {
+//!     Loading
+//!     Ready { Menu Game }
+//!     Exiting
+//! }
+//! ```
+
+mod source;
+#[cfg(test)]
+mod tests;
+
+use std::path::PathBuf;
+
+use bevy_state_plugin_generator::prelude::{Diagnostic, GeneratorError, PluginConfig,
+                                           generate_tokens_with_diagnostics};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::LitStr;
+
+use crate::source::SpannedSource;
+
+/// Expand an inline state definition into the states module and plugin.
+/// Uses the default [`PluginConfig`].
+#[proc_macro]
+pub fn bevy_states(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    expand_states(input.into()).into()
+}
+
+/// Expand the state definition in a file, relative to `CARGO_MANIFEST_DIR`,
+/// into the states module and plugin. Uses the default [`PluginConfig`].
+#[proc_macro]
+pub fn include_states(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    match syn::parse::<LitStr>(input) {
+        Ok(path) => expand_include_states(&path),
+        Err(error) => error.into_compile_error(),
+    }
+    .into()
+}
+
+/// Include a plugin generated into `OUT_DIR` by `generate_plugin_to_out_dir`.
+/// `include_generated_states!("states.rs")` is short for
+/// `include!(concat!(env!("OUT_DIR"), "/states.rs"))`.
+#[proc_macro]
+pub fn include_generated_states(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    match syn::parse::<LitStr>(input) {
+        Ok(file_name) => expand_include_generated_states(&file_name),
+        Err(error) => error.into_compile_error(),
+    }
+    .into()
+}
+
+pub(crate) fn expand_include_generated_states(file_name: &LitStr) -> TokenStream {
+    let file_name = format!("/{}", file_name.value().trim_start_matches('/'));
+    quote!(include!(concat!(env!("OUT_DIR"), #file_name));)
+}
+
+pub(crate) fn expand_states(input: TokenStream) -> TokenStream {
+    let source = SpannedSource::from(input);
+    match generate_tokens_with_diagnostics(&source, PluginConfig::default()) {
+        Ok((states, warnings)) => {
+            emit_warnings(warnings);
+            states
+        }
+        Err(error) => {
+            let span = error
+                .unparsed_input()
+                .and_then(|unparsed| source.span_of_suffix(unparsed))
+                .unwrap_or_else(Span::call_site);
+            compile_error(span, &error)
+        }
+    }
+}
+
+fn expand_include_states(path: &LitStr) -> TokenStream {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let full_path = PathBuf::from(manifest_dir).join(path.value());
+    let source = match std::fs::read_to_string(&full_path) {
+        Ok(source) => source,
+        Err(error) => {
+            let message = format!("failed to read '{}': {error}", full_path.display());
+            return syn::Error::new(path.span(), message).into_compile_error();
+        }
+    };
+    let states = match generate_tokens_with_diagnostics(&source, PluginConfig::default()) {
+        Ok((states, warnings)) => {
+            emit_warnings(warnings.into_iter().map(|mut warning| {
+                warning.path = Some(PathBuf::from(path.value()));
+                warning
+            }));
+            states
+        }
+        Err(error) => {
+            let location = error
+                .unparsed_input()
+                .map(|unparsed| line_and_column(&source, unparsed))
+                .map(|(line, column)| format!(" (at {}:{line}:{column})", path.value()))
+                .unwrap_or_default();
+            let message = format!("{error}{location}");
+            return syn::Error::new(path.span(), message).into_compile_error();
+        }
+    };
+    // let the compiler track the file, so changes trigger a rebuild
+    let full_path = full_path.to_string_lossy();
+    quote! {
+        const _: &str = include_str!(#full_path);
+        #states
+    }
+}
+
+/// A proc-macro cannot raise warnings of its own, so they go to stderr, which
+/// cargo shows with the rest of the compiler output
+fn emit_warnings(warnings: impl IntoIterator<Item = Diagnostic>) {
+    for warning in warnings {
+        eprintln!("warning: {warning}");
+    }
+}
+
+fn compile_error(span: Span, error: &GeneratorError) -> TokenStream {
+    syn::Error::new(span, error).into_compile_error()
+}
+
+/// 1-based line and column at which `suffix` starts in `source`
+pub(crate) fn line_and_column(source: &str, suffix: &str) -> (usize, usize) {
+    let offset = source.len() - suffix.len();
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+    (line, column)
+}
@@ -0,0 +1,68 @@
+use std::ops;
+
+use proc_macro2::{Delimiter, Span, TokenStream, TokenTree};
+
+/// The textual form of a macro input, remembering where each token came from
+#[derive(Debug, Default)]
+pub(crate) struct SpannedSource {
+    source: String,
+    spans: Vec<(usize, Span)>,
+}
+
+impl SpannedSource {
+    fn push(&mut self, text: &str, span: Span) {
+        if text.is_empty() {
+            return;
+        }
+        if !self.source.is_empty() {
+            self.source.push(' ');
+        }
+        self.spans.push((self.source.len(), span));
+        self.source.push_str(text);
+    }
+
+    fn push_tokens(&mut self, tokens: TokenStream) {
+        for tree in tokens {
+            match tree {
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::None => ("", ""),
+                    };
+                    self.push(open, group.span_open());
+                    self.push_tokens(group.stream());
+                    self.push(close, group.span_close());
+                }
+                tree => self.push(&tree.to_string(), tree.span()),
+            }
+        }
+    }
+
+    /// The span of the token at which `suffix` (a suffix of the source) starts
+    pub fn span_of_suffix(&self, suffix: &str) -> Option<Span> {
+        let offset = self.source.len().checked_sub(suffix.trim_start().len())?;
+        self.spans
+            .iter()
+            .take_while(|(start, _)| *start <= offset)
+            .last()
+            .map(|(_, span)| *span)
+    }
+}
+
+impl From<TokenStream> for SpannedSource {
+    fn from(tokens: TokenStream) -> Self {
+        let mut source = Self::default();
+        source.push_tokens(tokens);
+        source
+    }
+}
+
+impl ops::Deref for SpannedSource {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.source
+    }
+}
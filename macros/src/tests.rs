@@ -0,0 +1,67 @@
+use std::str::FromStr;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use rstest::rstest;
+use speculoos::prelude::*;
+
+use crate::source::SpannedSource;
+use crate::{expand_include_generated_states, expand_states, line_and_column};
+
+#[rstest]
+#[case(quote!(A), "A")]
+#[case(quote!(Loading Ready { Menu Game } Exiting), "Loading Ready { Menu Game } Exiting")]
+#[case(quote!(Root [ A, B ]), "Root [ A , B ]")]
+#[case(quote!(A {}), "A { }")]
+fn test_spanned_source_text(#[case] tokens: TokenStream, #[case] expected: &str) {
+    let source = SpannedSource::from(tokens);
+    assert_that!(source.to_string()).is_equal_to(expected.to_string());
+}
+
+#[rstest]
+#[case("; C", (2, 5))]
+#[case("{ B } ; C", (1, 3))]
+#[case("B } ; C", (2, 1))]
+fn test_span_of_suffix(#[case] suffix: &str, #[case] expected: (usize, usize)) {
+    let tokens = TokenStream::from_str("A {\nB } ; C").unwrap();
+    let source = SpannedSource::from(tokens);
+    let start = source.span_of_suffix(suffix).unwrap().start();
+    assert_that!((start.line, start.column + 1)).is_equal_to(expected);
+}
+
+#[rstest]
+#[case("A", "A", (1, 1))]
+#[case("A\nB ;", ";", (2, 3))]
+#[case("A\n", "", (2, 1))]
+fn test_line_and_column(
+    #[case] source: &str,
+    #[case] suffix: &str,
+    #[case] expected: (usize, usize),
+) {
+    assert_that!(line_and_column(source, suffix)).is_equal_to(expected);
+}
+
+#[rstest]
+fn test_expand_states() {
+    let output = expand_states(quote!(Loading Ready { Menu Game } Exiting)).to_string();
+    assert_that!(output.as_str()).contains("GeneratedStatesPlugin");
+    assert_that!(output.as_str()).does_not_contain("compile_error");
+}
+
+#[rstest]
+fn test_expand_states_reports_invalid_tokens() {
+    let tokens = TokenStream::from_str("Loading\nReady { menu }").unwrap();
+    let output = expand_states(tokens).to_string();
+    assert_that!(output.as_str()).contains("compile_error");
+    assert_that!(output.as_str()).contains("menu }");
+}
+
+#[rstest]
+#[case("states.rs")]
+#[case("/states.rs")]
+fn test_expand_include_generated_states(#[case] file_name: &str) {
+    let file_name = syn::LitStr::new(file_name, proc_macro2::Span::call_site());
+    let output = expand_include_generated_states(&file_name);
+    assert_that!(output.to_string())
+        .is_equal_to(quote!(include!(concat!(env!("OUT_DIR"), "/states.rs"));).to_string());
+}
@@ -0,0 +1,3 @@
+fn main() -> std::process::ExitCode {
+    bevy_state_plugin_generator::cli::main()
+}
@@ -0,0 +1,370 @@
+//! The `bspg` command-line tool, available with the `cli` feature
+
+#[cfg(test)]
+mod tests;
+
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand};
+
+use crate::generate::core::parse_nodes;
+use crate::generate::diagnostics::Diagnostic;
+use crate::generate::generator::Generator;
+use crate::generate::output::{GenerationOutcome, check_up_to_date, write_if_changed};
+use crate::generate::{GeneratorError, format_states};
+use crate::parsing::header::parse_source_header;
+use crate::prelude::{HeaderStyle, NamingScheme, PluginConfig, PluginName, RustfmtFailure};
+use crate::processing::{NodeData, process_nodes};
+
+/// Exit code when a checked file is out of date
+pub const EXIT_OUT_OF_DATE: u8 = 1;
+/// Exit code when a file could not be read or written
+pub const EXIT_IO: u8 = 3;
+/// Exit code when a state definition could not be parsed
+pub const EXIT_PARSE: u8 = 4;
+/// Exit code when the states could not be turned into a plugin
+pub const EXIT_PROCESSING: u8 = 5;
+/// Exit code when a generated file was modified by hand and not overwritten
+pub const EXIT_MODIFIED: u8 = 6;
+
+/// Generate Bevy state plugins from simple state definitions
+#[derive(Debug, Parser)]
+#[command(name = "bspg", version, about)]
+pub struct Cli {
+    /// What to do
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// The subcommands of `bspg`
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Generate a plugin from the states in <SRC> and write it to <DST>, or to
+    /// the `// bspg:output` of <SRC> when no <DST> is given
+    Generate {
+        /// The state definition
+        src: PathBuf,
+        /// The generated Rust file
+        dst: Option<PathBuf>,
+        /// Plugin configuration
+        #[command(flatten)]
+        config: ConfigArgs,
+    },
+    /// Update a template (a Rust file starting with `// bspg:` comments) in place
+    Update {
+        /// The template to update
+        template: PathBuf,
+        /// Plugin configuration
+        #[command(flatten)]
+        config: ConfigArgs,
+    },
+    /// Check that <DST> is up-to-date with <SRC>, or that a template is when
+    /// only one path is given
+    Check {
+        /// The state definition, or the template
+        src: PathBuf,
+        /// The generated Rust file
+        dst: Option<PathBuf>,
+        /// Plugin configuration
+        #[command(flatten)]
+        config: ConfigArgs,
+    },
+    /// Reformat a state definition in place
+    Fmt {
+        /// The state definition
+        src: PathBuf,
+        /// Only check if the file is formatted
+        #[arg(long)]
+        check: bool,
+    },
+    /// Print the resolved name of every state
+    Tree {
+        /// The state definition
+        src: PathBuf,
+        /// Plugin configuration
+        #[command(flatten)]
+        config: ConfigArgs,
+    },
+    /// Regenerate <DST> whenever <SRC> changes, or update a template whenever
+    /// it changes when only one path is given
+    #[cfg(feature = "watch")]
+    Watch {
+        /// The state definition, or the template
+        src: PathBuf,
+        /// The generated Rust file
+        dst: Option<PathBuf>,
+        /// Plugin configuration
+        #[command(flatten)]
+        config: ConfigArgs,
+    },
+}
+
+/// Flags for every [`PluginConfig`] field; unset flags keep the default
+#[derive(Args, Debug, Default)]
+pub struct ConfigArgs {
+    /// Plugin struct (`UpperCamelCase`) or function (`snake_case`) name
+    #[arg(long, value_parser = parse_plugin_name)]
+    pub plugin_name: Option<PluginName<'static>>,
+    /// Name of the root state
+    #[arg(long, conflicts_with = "no_root_state")]
+    pub root_state_name: Option<String>,
+    /// Do not wrap the states in an implicit root state
+    #[arg(long)]
+    pub no_root_state: bool,
+    /// Name of the module containing the generated states
+    #[arg(long)]
+    pub states_module_name: Option<String>,
+    /// How states are named: full, short or none
+    #[arg(long, value_parser = parse_naming_scheme)]
+    pub naming_scheme: Option<NamingScheme>,
+    /// Additional traits to derive, separated by commas
+    #[arg(long, value_delimiter = ',')]
+    pub additional_derives: Vec<String>,
+    /// Fail on problems with the `// bspg:` directives instead of warning
+    #[arg(long)]
+    pub strict: bool,
+    /// Overwrite generated files even if they were modified by hand
+    #[arg(long)]
+    pub force: bool,
+    /// What the header of generated files contains: full, minimal, source_hash or none
+    #[arg(long, value_parser = parse_header_style)]
+    pub header_style: Option<HeaderStyle>,
+    /// The edition rustfmt formats for, like 2024
+    #[arg(long)]
+    pub rustfmt_edition: Option<String>,
+    /// The `rustfmt.toml` to format with, or the directory containing it
+    #[arg(long)]
+    pub rustfmt_config_path: Option<PathBuf>,
+    /// A rustfmt option as key=value, can be repeated
+    #[arg(long, value_parser = parse_rustfmt_option)]
+    pub rustfmt_option: Vec<(String, String)>,
+    /// What happens when rustfmt fails: ignore, warn or error
+    #[arg(long, value_parser = parse_rustfmt_failure)]
+    pub rustfmt_on_failure: Option<RustfmtFailure>,
+}
+
+fn parse_plugin_name(input: &str) -> Result<PluginName<'static>, String> {
+    PluginName::parse(input.to_string())
+        .ok_or_else(|| format!("expected UpperCamelCase or snake_case, got '{input}'"))
+}
+
+fn parse_naming_scheme(input: &str) -> Result<NamingScheme, String> {
+    NamingScheme::try_parse(input)
+        .ok_or_else(|| format!("expected one of [none, short, full], got '{input}'"))
+}
+
+fn parse_header_style(input: &str) -> Result<HeaderStyle, String> {
+    HeaderStyle::try_parse(input)
+        .ok_or_else(|| format!("expected one of [full, minimal, source_hash, none], got '{input}'"))
+}
+
+fn parse_rustfmt_option(input: &str) -> Result<(String, String), String> {
+    input
+        .split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .filter(|(key, _)| !key.is_empty())
+        .ok_or_else(|| format!("expected key=value, got '{input}'"))
+}
+
+fn parse_rustfmt_failure(input: &str) -> Result<RustfmtFailure, String> {
+    RustfmtFailure::try_parse(input)
+        .ok_or_else(|| format!("expected one of [ignore, warn, error], got '{input}'"))
+}
+
+impl From<ConfigArgs> for PluginConfig {
+    fn from(args: ConfigArgs) -> Self {
+        let mut config = PluginConfig::default();
+        if let Some(plugin_name) = args.plugin_name {
+            config.plugin_name = plugin_name;
+        }
+        if args.no_root_state {
+            config.root_state_name = None;
+        } else if let Some(root_state_name) = args.root_state_name {
+            config.root_state_name = Some(Cow::Owned(root_state_name));
+        }
+        if let Some(states_module_name) = args.states_module_name {
+            config.states_module_name = Cow::Owned(states_module_name);
+        }
+        if let Some(naming_scheme) = args.naming_scheme {
+            config.naming_scheme = naming_scheme;
+        }
+        if let Some(header_style) = args.header_style {
+            config.header_style = header_style;
+        }
+        if let Some(edition) = args.rustfmt_edition {
+            config.rustfmt = config.rustfmt.with_edition(edition);
+        }
+        if let Some(config_path) = args.rustfmt_config_path {
+            config.rustfmt = config.rustfmt.with_config_path(config_path);
+        }
+        for (key, value) in args.rustfmt_option {
+            config.rustfmt = config.rustfmt.with_option(key, value);
+        }
+        if let Some(on_failure) = args.rustfmt_on_failure {
+            config.rustfmt = config.rustfmt.with_on_failure(on_failure);
+        }
+        config
+            .with_strict(args.strict)
+            .with_force(args.force)
+            .with_additional_derives(args.additional_derives)
+    }
+}
+
+/// The exit code that matches the kind of error
+pub fn exit_code(error: &GeneratorError) -> u8 {
+    match error {
+        GeneratorError::Io { .. } => EXIT_IO,
+        GeneratorError::OutOfDate { .. } => EXIT_OUT_OF_DATE,
+        GeneratorError::ModifiedByHand { .. } => EXIT_MODIFIED,
+        GeneratorError::Processing { error, .. } if error.unparsed_input().is_some() => EXIT_PARSE,
+        GeneratorError::Batch { errors } => errors
+            .first()
+            .map_or(EXIT_PROCESSING, |(_, error)| exit_code(error)),
+        GeneratorError::Processing { .. }
+        | GeneratorError::Directive { .. }
+        | GeneratorError::Rustfmt(_)
+        | GeneratorError::InvalidConfigFile { .. }
+        | GeneratorError::OutputWithoutSource { .. } => EXIT_PROCESSING,
+    }
+}
+
+/// Run `bspg` with the arguments of this process
+pub fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli, &mut io::stdout(), &mut io::stderr()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            let code = exit_code(&error);
+            #[cfg(feature = "fancy-diagnostics")]
+            eprintln!("{:?}", error.report());
+            #[cfg(not(feature = "fancy-diagnostics"))]
+            eprintln!("error: {error}");
+            ExitCode::from(code)
+        }
+    }
+}
+
+/// Run a parsed command, writing any report to `output` and warnings to `errors`
+pub fn run(
+    cli: Cli,
+    output: &mut impl Write,
+    errors: &mut impl Write,
+) -> Result<(), GeneratorError> {
+    match cli.command {
+        Command::Generate { src, dst, config } => {
+            let generator = Generator::new().cargo(false).source(src);
+            let outcome = match dst {
+                Some(dst) => generator.output(dst, config.into()),
+                None => generator.output_from_source(config.into()),
+            }
+            .run_single()?;
+            report_outcome(&outcome, output, errors).map_err(Into::into)
+        }
+        Command::Update { template, config } => {
+            let outcome = Generator::new()
+                .cargo(false)
+                .template(template, config.into())
+                .run_single()?;
+            report_outcome(&outcome, output, errors).map_err(Into::into)
+        }
+        Command::Check { src, dst, config } => {
+            let generator = Generator::new().cargo(false).check(true);
+            let outcome = match dst {
+                Some(dst) => generator.source(src).output(dst, config.into()),
+                None => generator.template(src, config.into()),
+            }
+            .run_single()?;
+            report_warnings(&outcome.warnings, errors)?;
+            writeln!(output, "up-to-date: {}", outcome.path.display())?;
+            Ok(())
+        }
+        Command::Fmt { src, check } => {
+            let source =
+                fs::read_to_string(&src).map_err(|error| GeneratorError::io(error, &src))?;
+            let formatted = format_states(&source).map_err(|error| error.in_file(&src))?;
+            if check {
+                check_up_to_date(&src, &formatted)?;
+                writeln!(output, "formatted: {}", src.display())?;
+                Ok(())
+            } else {
+                let outcome = write_if_changed(&src, &formatted, false)?;
+                report_outcome(&outcome, output, errors).map_err(Into::into)
+            }
+        }
+        Command::Tree { src, config } => {
+            let mut config = PluginConfig::from(config);
+            let source =
+                fs::read_to_string(&src).map_err(|error| GeneratorError::io(error, &src))?;
+            let header = parse_source_header(&source, &mut config)?;
+            let nodes = parse_nodes(&header.states)
+                .and_then(|nodes| {
+                    process_nodes(nodes, config.naming_scheme, config.root_state_name)
+                        .map_err(|error| error.locate(&header.states).into())
+                })
+                .map_err(|error| error.relocate(&header.source_map).in_file(&src))?;
+            print_tree(&nodes, None, output).map_err(Into::into)
+        }
+        #[cfg(feature = "watch")]
+        Command::Watch { src, dst, config } => {
+            use crate::watch::{StateWatcher, WatchTarget};
+            let target = match dst {
+                Some(dst) => WatchTarget::Plugin { src, dst },
+                None => WatchTarget::Template(src),
+            };
+            writeln!(output, "watching: {}", target.source().display())?;
+            // errors are reported inline, only a failing output ends the watch
+            let mut result = Ok(());
+            StateWatcher::new(target, config.into())?.run(|outcome| {
+                if result.is_ok() {
+                    result = match outcome {
+                        Ok(outcome) => report_outcome(&outcome, output, errors),
+                        Err(error) => writeln!(errors, "error: {error}"),
+                    }
+                    .and_then(|_| output.flush())
+                    .and_then(|_| errors.flush());
+                }
+            });
+            result.map_err(Into::into)
+        }
+    }
+}
+
+/// Print the subtree below `parent`, depth-first
+fn print_tree(
+    nodes: &[NodeData],
+    parent: Option<usize>,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    for node in nodes.iter().filter(|node| node.parent == parent) {
+        let indent = "    ".repeat(node.depth);
+        let resolved_name = node.resolved_name.as_deref().unwrap_or_default();
+        writeln!(output, "{indent}{} -> {resolved_name}", node.name)?;
+        print_tree(nodes, Some(node.index), output)?;
+    }
+    Ok(())
+}
+
+fn report_outcome(
+    outcome: &GenerationOutcome,
+    output: &mut impl Write,
+    errors: &mut impl Write,
+) -> io::Result<()> {
+    report_warnings(&outcome.warnings, errors)?;
+    let status = if outcome.changed {
+        "wrote"
+    } else {
+        "unchanged"
+    };
+    writeln!(output, "{status}: {}", outcome.path.display())
+}
+
+fn report_warnings(warnings: &[Diagnostic], errors: &mut impl Write) -> io::Result<()> {
+    for warning in warnings {
+        writeln!(errors, "warning: {warning}")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,237 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+
+use clap::Parser;
+use insta::assert_snapshot;
+use rstest::rstest;
+use speculoos::prelude::*;
+use tempfile::TempDir;
+
+use crate::cli::{Cli, Command, EXIT_IO, EXIT_MODIFIED, EXIT_OUT_OF_DATE, EXIT_PARSE,
+                 EXIT_PROCESSING, exit_code, run};
+use crate::prelude::{HeaderStyle, NamingScheme, PluginConfig, PluginName, RustfmtConfig,
+                     RustfmtFailure};
+
+fn run_args<S: AsRef<str>>(dir: &Path, args: &[S]) -> Result<String, u8> {
+    run_args_with_errors(dir, args).map(|(output, _)| output)
+}
+
+/// What `run` wrote to stdout and to stderr
+fn run_args_with_errors<S: AsRef<str>>(dir: &Path, args: &[S]) -> Result<(String, String), u8> {
+    let args = args
+        .iter()
+        .map(|arg| arg.as_ref().replace("$DIR", &dir.to_string_lossy()));
+    let cli = Cli::try_parse_from(["bspg".to_string()].into_iter().chain(args)).unwrap();
+    let (mut output, mut errors) = (Vec::new(), Vec::new());
+    run(cli, &mut output, &mut errors)
+        .map(|_| {
+            (
+                String::from_utf8(output).unwrap(),
+                String::from_utf8(errors).unwrap(),
+            )
+        })
+        .map_err(|error| exit_code(&error))
+}
+
+#[rstest]
+fn test_config_args_map_onto_plugin_config() {
+    let cli = Cli::try_parse_from([
+        "bspg",
+        "tree",
+        "states.txt",
+        "--plugin-name",
+        "my_plugin",
+        "--no-root-state",
+        "--states-module-name",
+        "inner",
+        "--naming-scheme",
+        "short",
+        "--additional-derives",
+        "PartialOrd,Ord",
+        "--strict",
+        "--force",
+        "--header-style",
+        "source_hash",
+        "--rustfmt-edition",
+        "2024",
+        "--rustfmt-config-path",
+        "rustfmt.toml",
+        "--rustfmt-option",
+        "max_width=80",
+        "--rustfmt-option",
+        "hard_tabs = true",
+        "--rustfmt-on-failure",
+        "warn",
+    ])
+    .unwrap();
+    let Command::Tree { config, .. } = cli.command else {
+        panic!("expected the tree command");
+    };
+    let config = PluginConfig::from(config);
+    assert_that!(config.plugin_name).is_equal_to(PluginName::new_function("my_plugin"));
+    assert_that!(config.root_state_name).is_none();
+    assert_that!(config.states_module_name).is_equal_to(Cow::from("inner"));
+    assert_that!(config.naming_scheme).is_equal_to(NamingScheme::Short);
+    assert_that!(config.additional_derives)
+        .is_equal_to(vec![Cow::from("PartialOrd"), Cow::from("Ord")]);
+    assert_that!(config.strict).is_true();
+    assert_that!(config.force).is_true();
+    assert_that!(config.header_style).is_equal_to(HeaderStyle::SourceHash);
+    assert_that!(config.rustfmt).is_equal_to(
+        RustfmtConfig::default()
+            .with_edition("2024")
+            .with_config_path("rustfmt.toml")
+            .with_option("max_width", 80)
+            .with_option("hard_tabs", true)
+            .with_on_failure(RustfmtFailure::Warn),
+    );
+}
+
+#[rstest]
+#[case::conflicting_root(&["--root-state-name", "Root", "--no-root-state"])]
+#[case::naming_scheme(&["--naming-scheme", "long"])]
+#[case::plugin_name(&["--plugin-name", "_plugin"])]
+#[case::header_style(&["--header-style", "short"])]
+#[case::rustfmt_option(&["--rustfmt-option", "max_width"])]
+#[case::rustfmt_on_failure(&["--rustfmt-on-failure", "panic"])]
+fn test_config_args_rejected(#[case] flags: &[&str]) {
+    let args = ["bspg", "tree", "states.txt"].iter().chain(flags);
+    assert_that!(Cli::try_parse_from(args)).is_err();
+}
+
+#[rstest]
+fn test_tree() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("states.txt"),
+        "Loading { Assets } Ready { Menu Game }",
+    )
+    .unwrap();
+    let output = run_args(
+        dir.path(),
+        &["tree", "$DIR/states.txt", "--naming-scheme", "short"],
+    )
+    .unwrap();
+    assert_snapshot!(output, @r"
+    GameState -> GameState
+        Loading -> GameStateLoading
+            Assets -> LoadingAssets
+        Ready -> GameStateReady
+            Menu -> ReadyMenu
+            Game -> ReadyGame
+    ");
+}
+
+#[rstest]
+fn test_generate_and_check() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("states.txt"), "Loading Ready").unwrap();
+    let generate = ["generate", "$DIR/states.txt", "$DIR/states.rs"];
+    let check = ["check", "$DIR/states.txt", "$DIR/states.rs"];
+
+    assert_that!(run_args(dir.path(), &check)).is_err_containing(EXIT_OUT_OF_DATE);
+    assert_that!(run_args(dir.path(), &generate).unwrap()).starts_with("wrote: ");
+    assert_that!(run_args(dir.path(), &generate).unwrap()).starts_with("unchanged: ");
+    assert_that!(run_args(dir.path(), &check).unwrap()).starts_with("up-to-date: ");
+}
+
+#[rstest]
+fn test_generate_refuses_hand_edits() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("states.txt"), "Loading Ready").unwrap();
+    let generate = ["generate", "$DIR/states.txt", "$DIR/states.rs"];
+    run_args(dir.path(), &generate).unwrap();
+    let dst = dir.path().join("states.rs");
+    let edited = fs::read_to_string(&dst)
+        .unwrap()
+        .replace("Ready", "Waiting");
+    fs::write(&dst, edited).unwrap();
+
+    assert_that!(run_args(dir.path(), &generate)).is_err_containing(EXIT_MODIFIED);
+    let forced = run_args(dir.path(), &[&generate[..], &["--force"]].concat());
+    assert_that!(forced.unwrap()).starts_with("wrote: ");
+}
+
+#[rstest]
+fn test_generate_to_output_directive() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("ui.txt"),
+        "// bspg:output \"ui_states.rs\"\nHud Menu",
+    )
+    .unwrap();
+    let output = run_args(dir.path(), &["generate", "$DIR/ui.txt"]).unwrap();
+    assert_that!(output.as_str()).ends_with("ui_states.rs\n");
+    assert_that!(dir.path().join("ui_states.rs").exists()).is_true();
+}
+
+#[rstest]
+fn test_update_and_check_template() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("states.rs"), "// bspg:\n// Loading Ready\n").unwrap();
+    let check = ["check", "$DIR/states.rs"];
+
+    assert_that!(run_args(dir.path(), &check)).is_err_containing(EXIT_OUT_OF_DATE);
+    assert_that!(run_args(dir.path(), &["update", "$DIR/states.rs"]).unwrap())
+        .starts_with("wrote: ");
+    assert_that!(run_args(dir.path(), &check).unwrap()).starts_with("up-to-date: ");
+}
+
+#[rstest]
+fn test_warnings_go_to_stderr() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("states.txt"),
+        "// bspg:nonsense 1\nLoading Ready",
+    )
+    .unwrap();
+    let generate = ["generate", "$DIR/states.txt", "$DIR/states.rs"];
+    let (output, errors) = run_args_with_errors(dir.path(), &generate).unwrap();
+    assert_that!(output.as_str()).starts_with("wrote: ");
+    assert_that!(errors.as_str()).starts_with("warning: ");
+}
+
+#[rstest]
+fn test_fmt() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("states.txt");
+    fs::write(&path, "Loading, Ready{Menu Game}").unwrap();
+    let check = ["fmt", "$DIR/states.txt", "--check"];
+
+    assert_that!(run_args(dir.path(), &check)).is_err_containing(EXIT_OUT_OF_DATE);
+    assert_that!(run_args(dir.path(), &["fmt", "$DIR/states.txt"])).is_ok();
+    assert_that!(run_args(dir.path(), &check)).is_ok();
+    assert_that!(fs::read_to_string(path).unwrap())
+        .is_equal_to("Loading\nReady {\n    Menu\n    Game\n}\n".to_string());
+}
+
+#[rstest]
+#[case::io(None, EXIT_IO)]
+#[case::parse(Some("Loading { ready }"), EXIT_PARSE)]
+#[case::processing(Some("A B A"), EXIT_PROCESSING)]
+fn test_exit_codes(#[case] source: Option<&str>, #[case] expected: u8) {
+    let dir = TempDir::new().unwrap();
+    if let Some(source) = source {
+        fs::write(dir.path().join("states.txt"), source).unwrap();
+    }
+    let generate = [
+        "generate",
+        "$DIR/states.txt",
+        "$DIR/states.rs",
+        "--naming-scheme",
+        "none",
+    ];
+    assert_that!(run_args(dir.path(), &generate)).is_err_containing(expected);
+}
+
+#[cfg(feature = "watch")]
+#[rstest]
+#[case::plugin(&["bspg", "watch", "states.txt", "states.rs"], Some("states.rs"))]
+#[case::template(&["bspg", "watch", "states.rs"], None)]
+fn test_parse_watch(#[case] args: &[&str], #[case] expected_dst: Option<&str>) {
+    let Command::Watch { dst, .. } = Cli::try_parse_from(args).unwrap().command else {
+        panic!("expected the watch command");
+    };
+    assert_that!(dst).is_equal_to(expected_dst.map(Into::into));
+}
@@ -1,15 +1,26 @@
+#[cfg(feature = "manifest")]
+pub(crate) mod manifest;
+
 use std::borrow::Cow;
 use std::ops;
+use std::path::PathBuf;
 
 #[cfg(test)]
 use bevy_reflect::Reflect;
 use itertools::Itertools;
+use proc_macro2::Ident;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::generate::GeneratorError;
+use crate::processing::{ProcessingError, parse_config_value};
 
 /// How state-names are determined
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(test, derive(Reflect))]
 pub enum NamingScheme {
     /// Name includes the names of all ancestors
+    #[default]
     Full,
     /// Name includes only the name of the immediate parent
     Short,
@@ -53,9 +64,25 @@ impl NamingScheme {
     }
 }
 
-impl Default for NamingScheme {
-    fn default() -> Self {
-        Self::Full
+/// Serialized as its tag
+#[cfg(feature = "serde")]
+impl Serialize for NamingScheme {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.tag())
+    }
+}
+
+/// Accepts the name or the tag, like [`NamingScheme::try_parse`]
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for NamingScheme {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let input = String::deserialize(deserializer)?;
+        Self::try_parse(&input).ok_or_else(|| {
+            serde::de::Error::invalid_value(
+                serde::de::Unexpected::Str(&input),
+                &"one of [none, short, full]",
+            )
+        })
     }
 }
 
@@ -65,6 +92,170 @@ impl std::fmt::Display for NamingScheme {
     }
 }
 
+/// What the comment header of a generated file contains
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(test, derive(Reflect))]
+pub enum HeaderStyle {
+    /// The crate version, the source path, the hash of the code and the states
+    #[default]
+    Full,
+    /// Like [`HeaderStyle::Full`], without the crate version and the states,
+    /// so updating this crate does not change the generated files
+    Minimal,
+    /// Only the hash of the code, which protects it against hand edits
+    SourceHash,
+    /// No header at all
+    None,
+}
+
+impl HeaderStyle {
+    /// Get the identifying tag for this style
+    /// ```rust
+    /// # use bevy_state_plugin_generator::prelude::HeaderStyle;
+    /// assert_eq!(HeaderStyle::Full.tag(), "full");
+    /// assert_eq!(HeaderStyle::Minimal.tag(), "minimal");
+    /// assert_eq!(HeaderStyle::SourceHash.tag(), "source_hash");
+    /// assert_eq!(HeaderStyle::None.tag(), "none");
+    /// ```
+    pub fn tag(&self) -> &str {
+        match self {
+            HeaderStyle::Full => "full",
+            HeaderStyle::Minimal => "minimal",
+            HeaderStyle::SourceHash => "source_hash",
+            HeaderStyle::None => "none",
+        }
+    }
+    /// Convert a string into a HeaderStyle, if it's the tag
+    pub fn try_parse(input: &str) -> Option<Self> {
+        match input {
+            "full" => Some(HeaderStyle::Full),
+            "minimal" => Some(HeaderStyle::Minimal),
+            "source_hash" => Some(HeaderStyle::SourceHash),
+            "none" => Some(HeaderStyle::None),
+            _ => None,
+        }
+    }
+}
+
+/// Serialized as its tag
+#[cfg(feature = "serde")]
+impl Serialize for HeaderStyle {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.tag())
+    }
+}
+
+/// Accepts the tag, like [`HeaderStyle::try_parse`]
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for HeaderStyle {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let input = String::deserialize(deserializer)?;
+        Self::try_parse(&input).ok_or_else(|| {
+            serde::de::Error::invalid_value(
+                serde::de::Unexpected::Str(&input),
+                &"one of [full, minimal, source_hash, none]",
+            )
+        })
+    }
+}
+
+/// What happens when `rustfmt` cannot format the generated code
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(test, derive(Reflect))]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum RustfmtFailure {
+    /// Keep the code as formatted by `prettyplease`
+    #[default]
+    Ignore,
+    /// Keep the code as formatted by `prettyplease` and report a
+    /// [`Diagnostic`](crate::prelude::Diagnostic)
+    Warn,
+    /// Fail with [`GeneratorError::Rustfmt`]
+    Error,
+}
+
+impl RustfmtFailure {
+    /// Convert a string into a RustfmtFailure, if it's the lowercase name
+    pub fn try_parse(input: &str) -> Option<Self> {
+        match input {
+            "ignore" => Some(RustfmtFailure::Ignore),
+            "warn" => Some(RustfmtFailure::Warn),
+            "error" => Some(RustfmtFailure::Error),
+            _ => None,
+        }
+    }
+}
+
+/// How `rustfmt` is run on the generated code, with the `rustfmt` feature
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(test, derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct RustfmtConfig {
+    /// Whether `rustfmt` runs at all; the code is still pretty-printed without it
+    pub enabled: bool,
+    /// `--edition`, like `2024`; rustfmt defaults to 2015
+    pub edition: Option<Cow<'static, str>>,
+    /// `--config-path`, a `rustfmt.toml` or the directory containing one
+    pub config_path: Option<PathBuf>,
+    /// `--config key=value,...`, these take precedence over the config file
+    pub options: Vec<(Cow<'static, str>, Cow<'static, str>)>,
+    /// What happens when `rustfmt` fails or is not installed
+    pub on_failure: RustfmtFailure,
+}
+
+impl Default for RustfmtConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RustfmtConfig {
+    /// Run `rustfmt` with its own defaults, ignoring failures
+    pub const fn new() -> Self {
+        Self {
+            enabled: true,
+            edition: None,
+            config_path: None,
+            options: Vec::new(),
+            on_failure: RustfmtFailure::Ignore,
+        }
+    }
+    /// Do not run `rustfmt`
+    pub const fn disabled() -> Self {
+        Self::new().with_enabled(false)
+    }
+    /// Turn running `rustfmt` on or off
+    pub const fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+    /// Format for the given edition
+    pub fn with_edition<S: ToString>(mut self, edition: S) -> Self {
+        self.edition = Some(Cow::Owned(edition.to_string()));
+        self
+    }
+    /// Use the `rustfmt.toml` at `path`
+    pub fn with_config_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.config_path = Some(path.into());
+        self
+    }
+    /// Set a single rustfmt option, like `max_width`
+    pub fn with_option<K: ToString, V: ToString>(mut self, key: K, value: V) -> Self {
+        let option = (Cow::Owned(key.to_string()), Cow::Owned(value.to_string()));
+        self.options.push(option);
+        self
+    }
+    /// Configure what happens when `rustfmt` fails
+    pub fn with_on_failure(mut self, on_failure: RustfmtFailure) -> Self {
+        self.on_failure = on_failure;
+        self
+    }
+}
+
 /// How the plugin is rendered.
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(test, derive(Reflect))]
@@ -96,6 +287,54 @@ impl<'s> PluginName<'s> {
     }
 }
 
+/// Serialized as the plain name when the variant follows from its case, as an
+/// explicit `{ "Struct": name }` / `{ "Function": name }` otherwise
+#[cfg(feature = "serde")]
+impl Serialize for PluginName<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if PluginName::parse(&**self).as_ref() == Some(self) {
+            return serializer.serialize_str(self);
+        }
+        match self {
+            PluginName::Struct(name) => {
+                serializer.serialize_newtype_variant("PluginName", 0, "Struct", name)
+            }
+            PluginName::Function(name) => {
+                serializer.serialize_newtype_variant("PluginName", 1, "Function", name)
+            }
+        }
+    }
+}
+
+/// Accepts a plain name, inferring the variant like [`PluginName::parse`],
+/// or an explicit `{ "Struct": name }` / `{ "Function": name }`
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for PluginName<'static> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Inferred(String),
+            Explicit(Explicit),
+        }
+        #[derive(Deserialize)]
+        enum Explicit {
+            Struct(String),
+            Function(String),
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Inferred(name) => PluginName::parse(name.clone()).ok_or_else(|| {
+                serde::de::Error::invalid_value(
+                    serde::de::Unexpected::Str(&name),
+                    &"an UpperCamelCase or snake_case name",
+                )
+            }),
+            Repr::Explicit(Explicit::Struct(name)) => Ok(PluginName::new_struct(name)),
+            Repr::Explicit(Explicit::Function(name)) => Ok(PluginName::new_function(name)),
+        }
+    }
+}
+
 impl<'a> ops::Deref for PluginName<'a> {
     type Target = str;
 
@@ -152,8 +391,21 @@ impl<'s> TryFrom<Cow<'s, str>> for PluginName<'s> {
 }
 
 /// Configuration for the generated plugin
+///
+/// New settings may be added, so build it from [`PluginConfig::default`] with
+/// the `with_*` methods, or set the fields of a default one:
+/// ```rust
+/// # use bevy_state_plugin_generator::prelude::*;
+/// let mut config = PluginConfig::default()
+///     .with_naming_scheme(NamingScheme::Short)
+///     .without_root_state_name();
+/// config.strict = true;
+/// # assert!(config.root_state_name.is_none());
+/// ```
 #[derive(Clone, Debug)]
 #[cfg_attr(test, derive(Reflect))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+#[non_exhaustive]
 pub struct PluginConfig {
     /// Name of the struct that implements [`bevy::plugin::Plugin`]
     /// Defaults to `GeneratedStatesPlugin`
@@ -166,6 +418,15 @@ pub struct PluginConfig {
     pub naming_scheme: NamingScheme,
     /// These additional traits will be added to the derive list
     pub additional_derives: Vec<Cow<'static, str>>,
+    /// Fail on unknown settings, invalid values and dropped lines in the
+    /// `// bspg:` directives, instead of warning about them
+    pub strict: bool,
+    /// Overwrite generated files even if they were modified by hand
+    pub force: bool,
+    /// What the comment header of the generated file contains
+    pub header_style: HeaderStyle,
+    /// How `rustfmt` is run, with the `rustfmt` feature
+    pub rustfmt: RustfmtConfig,
 }
 
 impl PluginConfig {
@@ -176,6 +437,10 @@ impl PluginConfig {
             states_module_name: Cow::Borrowed("states"),
             naming_scheme: NamingScheme::Full,
             additional_derives: vec![],
+            strict: false,
+            force: false,
+            header_style: HeaderStyle::Full,
+            rustfmt: RustfmtConfig::new(),
         }
     }
     /// Set the plugin name to a struct name (`UpperCamelCase`)
@@ -198,11 +463,36 @@ impl PluginConfig {
         self.root_state_name = Some(Cow::Owned(name.to_string()));
         self
     }
+    /// Generate the states without a root enum/struct
+    pub fn without_root_state_name(mut self) -> Self {
+        self.root_state_name = None;
+        self
+    }
     /// Configure how generated states are named
     pub fn with_naming_scheme(mut self, scheme: NamingScheme) -> Self {
         self.naming_scheme = scheme;
         self
     }
+    /// Fail on problems with the `// bspg:` directives instead of warning
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+    /// Overwrite generated files even if they were modified by hand
+    pub fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+    /// Configure what the comment header of the generated file contains
+    pub fn with_header_style(mut self, header_style: HeaderStyle) -> Self {
+        self.header_style = header_style;
+        self
+    }
+    /// Configure how `rustfmt` is run, with the `rustfmt` feature
+    pub fn with_rustfmt(mut self, rustfmt: RustfmtConfig) -> Self {
+        self.rustfmt = rustfmt;
+        self
+    }
     /// Set additional traits to derive on the generated states
     pub fn with_additional_derives<S: ToString, D: IntoIterator<Item = S>>(
         mut self,
@@ -225,6 +515,33 @@ impl PluginConfig {
     }
 }
 
+impl PluginConfig {
+    /// Check that every configured name can be spliced into the generated source:
+    /// names must be identifiers and derives must be paths.
+    /// ```rust
+    /// # use bevy_state_plugin_generator::prelude::*;
+    /// assert!(PluginConfig::default().validate().is_ok());
+    /// let config = PluginConfig::default().with_states_module_name("\"states\"");
+    /// let error = config.validate().unwrap_err();
+    /// assert!(error.to_string().contains("states_module_name"));
+    /// ```
+    pub fn validate(&self) -> Result<(), GeneratorError> {
+        self.validate_fields().map_err(Into::into)
+    }
+
+    pub(crate) fn validate_fields(&self) -> Result<(), ProcessingError> {
+        parse_config_value::<Ident>("plugin_name", &self.plugin_name)?;
+        if let Some(root_state_name) = &self.root_state_name {
+            parse_config_value::<Ident>("root_state_name", root_state_name)?;
+        }
+        parse_config_value::<Ident>("states_module_name", &self.states_module_name)?;
+        for derive in &self.additional_derives {
+            parse_config_value::<syn::Path>("additional_derives", derive)?;
+        }
+        Ok(())
+    }
+}
+
 /// Default configuration for the generated plugin
 /// ```rust
 /// # use bevy_state_plugin_generator::prelude::*;
@@ -233,6 +550,10 @@ impl PluginConfig {
 /// assert_eq!(config.root_state_name, Some(Cow::from("GameState")));
 /// assert_eq!(config.states_module_name, Cow::from("states"));
 /// assert_eq!(config.naming_scheme, NamingScheme::Full);
+/// assert!(!config.strict);
+/// assert!(!config.force);
+/// assert_eq!(config.header_style, HeaderStyle::Full);
+/// assert_eq!(config.rustfmt, RustfmtConfig::default());
 /// ```
 impl Default for PluginConfig {
     fn default() -> Self {
@@ -258,3 +579,90 @@ fn test_plugin_config_from_naming_scheme(#[case] naming_scheme: NamingScheme) {
     let config = PluginConfig::from(naming_scheme);
     assert_eq!(config.naming_scheme, naming_scheme);
 }
+
+#[cfg(test)]
+#[rstest::rstest]
+#[case::plugin_name(PluginConfig::default().with_plugin_struct_name("My Plugin"), "plugin_name")]
+#[case::plugin_keyword(PluginConfig::default().with_plugin_fn_name("fn"), "plugin_name")]
+#[case::root_state_name(
+    PluginConfig::default().with_root_state_name("\"Root\""),
+    "root_state_name"
+)]
+#[case::states_module_name(
+    PluginConfig::default().with_states_module_name("my-states"),
+    "states_module_name"
+)]
+#[case::additional_derives(
+    PluginConfig::default().with_additional_derives(["serde::Serialize", "Partial Ord"]),
+    "additional_derives"
+)]
+fn test_plugin_config_validate_names_field(#[case] config: PluginConfig, #[case] field: &str) {
+    let error = config.validate().unwrap_err();
+    assert!(error.to_string().contains(&format!("`{field}`")), "{error}");
+}
+
+#[cfg(all(test, feature = "serde"))]
+#[rstest::rstest]
+#[case::defaults(PluginConfig::default())]
+#[case::customized(
+    PluginConfig::default()
+        .with_plugin_fn_name("my_plugin")
+        .with_states_module_name("inner")
+        .with_naming_scheme(NamingScheme::None)
+        .with_additional_derives(["PartialOrd", "Ord"])
+        .with_header_style(HeaderStyle::SourceHash)
+        .with_rustfmt(
+            RustfmtConfig::default()
+                .with_edition(2024)
+                .with_config_path("rustfmt.toml")
+                .with_option("max_width", 80)
+                .with_on_failure(RustfmtFailure::Warn)
+        )
+)]
+#[case::no_root_state(PluginConfig { root_state_name: None, ..Default::default() })]
+fn test_plugin_config_serde_round_trip(#[case] config: PluginConfig) {
+    let json = serde_json::to_string(&config).unwrap();
+    let parsed: PluginConfig = serde_json::from_str(&json).unwrap();
+    assert_eq!(format!("{parsed:?}"), format!("{config:?}"));
+}
+
+#[cfg(all(test, feature = "serde"))]
+#[rstest::rstest]
+#[case::inferred_struct(r#""MyPlugin""#, PluginName::new_struct("MyPlugin"))]
+#[case::inferred_function(r#""my_plugin""#, PluginName::new_function("my_plugin"))]
+#[case::explicit_struct(r#"{"Struct":"my_plugin"}"#, PluginName::new_struct("my_plugin"))]
+#[case::explicit_function(r#"{"Function":"MyPlugin"}"#, PluginName::new_function("MyPlugin"))]
+fn test_plugin_name_deserialize(#[case] json: &str, #[case] expected: PluginName<'static>) {
+    assert_eq!(serde_json::from_str::<PluginName>(json).unwrap(), expected);
+}
+
+#[cfg(all(test, feature = "serde"))]
+#[rstest::rstest]
+#[case::inferred_struct(PluginName::new_struct("MyPlugin"), r#""MyPlugin""#)]
+#[case::inferred_function(PluginName::new_function("my_plugin"), r#""my_plugin""#)]
+#[case::explicit_struct(PluginName::new_struct("snake_name"), r#"{"Struct":"snake_name"}"#)]
+#[case::explicit_function(PluginName::new_function("CamelName"), r#"{"Function":"CamelName"}"#)]
+fn test_plugin_name_serde_round_trip(#[case] name: PluginName<'static>, #[case] json: &str) {
+    assert_eq!(serde_json::to_string(&name).unwrap(), json);
+    assert_eq!(serde_json::from_str::<PluginName>(json).unwrap(), name);
+}
+
+#[cfg(all(test, feature = "serde"))]
+#[rstest::rstest]
+#[case::tag(r#""short""#, Some(NamingScheme::Short))]
+#[case::name(r#""Short""#, Some(NamingScheme::Short))]
+#[case::invalid(r#""long""#, None)]
+fn test_naming_scheme_deserialize(#[case] json: &str, #[case] expected: Option<NamingScheme>) {
+    assert_eq!(serde_json::from_str::<NamingScheme>(json).ok(), expected);
+}
+
+#[cfg(all(test, feature = "serde"))]
+#[rstest::rstest]
+fn test_plugin_config_deserialize_partial() {
+    let config: PluginConfig = serde_json::from_str(r#"{"naming_scheme":"none"}"#).unwrap();
+    assert_eq!(config.naming_scheme, NamingScheme::None);
+    assert_eq!(
+        config.plugin_name,
+        PluginName::new_struct("GeneratedStatesPlugin")
+    );
+}
@@ -0,0 +1,232 @@
+//! Load a [`PluginConfig`] from `[package.metadata.bspg]` or a `bspg.toml`
+
+#[cfg(test)]
+mod tests;
+
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+use itertools::Itertools;
+use toml::{Table, Value};
+
+use crate::generate::GeneratorError;
+use crate::parsing::directive::DirectiveValue;
+use crate::parsing::header::apply_directive;
+use crate::prelude::PluginConfig;
+
+/// Name of the settings file that is looked up next to a state definition
+pub(crate) const CONFIG_FILE_NAME: &str = "bspg.toml";
+
+/// Key of the table with per-source-file overrides
+const FILES_KEY: &str = "files";
+
+impl PluginConfig {
+    /// Load the configuration from `[package.metadata.bspg]` in the `Cargo.toml`
+    /// of the crate being built. Unset fields keep their default.
+    /// ```toml
+    /// [package.metadata.bspg]
+    /// plugin_name = "GameStatesPlugin"
+    /// naming_scheme = "short"
+    /// additional_derives = ["PartialOrd", "Ord"]
+    /// ```
+    /// ```rust no_run
+    /// use bevy_state_plugin_generator::prelude::*;
+    /// fn main() {
+    ///   let config = PluginConfig::from_manifest().expect("Invalid configuration!");
+    ///   generate_plugin("src/states.txt", "src/generated_states.rs", config)
+    ///     .expect("Failed to generate plugin!");
+    /// }
+    /// ```
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    pub fn from_manifest() -> Result<Self, GeneratorError> {
+        let manifest_dir = manifest_dir()?;
+        rerun_if_changed(&manifest_dir, None);
+        load_config(&manifest_dir, None)
+    }
+
+    /// Load the configuration for one state definition: `[package.metadata.bspg]`
+    /// first, then the `bspg.toml` next to `src`. Either can override settings
+    /// for single files in a `files` table, keyed by path relative to itself.
+    /// ```toml
+    /// # bspg.toml
+    /// root_state_name = "GameState"
+    ///
+    /// [files."ui.txt"]
+    /// plugin_name = "UiStatesPlugin"
+    /// root_state_name = "UiState"
+    /// ```
+    #[cfg_attr(coverage_nightly, coverage(off))]
+    pub fn from_manifest_for(src: impl AsRef<Path>) -> Result<Self, GeneratorError> {
+        let manifest_dir = manifest_dir()?;
+        rerun_if_changed(&manifest_dir, Some(src.as_ref()));
+        load_config(&manifest_dir, Some(src.as_ref()))
+    }
+}
+
+fn manifest_dir() -> io::Result<PathBuf> {
+    env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "CARGO_MANIFEST_DIR is not set, run this from a build script",
+            )
+        })
+}
+
+/// Tell cargo what to watch, but only from a build script: cargo sets `OUT_DIR`
+/// for those alone, and elsewhere the lines would just clutter stdout
+#[cfg_attr(coverage_nightly, coverage(off))]
+fn rerun_if_changed(manifest_dir: &Path, src: Option<&Path>) {
+    if env::var_os("OUT_DIR").is_some() {
+        for path in watched_paths(manifest_dir, src) {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+}
+
+/// The settings files that exist for `src`. A missing `bspg.toml` is watched
+/// through its directory instead, on purpose: cargo reruns the build when a
+/// file is added to a watched directory, so creating the file later picks it up.
+pub(crate) fn watched_paths(manifest_dir: &Path, src: Option<&Path>) -> Vec<PathBuf> {
+    let mut paths = vec![];
+    let manifest_path = manifest_dir.join("Cargo.toml");
+    if manifest_path.is_file() {
+        paths.push(manifest_path);
+    }
+    if let Some(src) = src {
+        let src = manifest_dir.join(src);
+        let config_dir = src.parent().unwrap_or(manifest_dir);
+        let config_path = config_dir.join(CONFIG_FILE_NAME);
+        paths.push(if config_path.is_file() {
+            config_path
+        } else {
+            config_dir.to_path_buf()
+        });
+    }
+    paths
+}
+
+/// Apply every settings table that concerns `src`, in order of precedence
+pub(crate) fn load_config(
+    manifest_dir: &Path,
+    src: Option<&Path>,
+) -> Result<PluginConfig, GeneratorError> {
+    let mut config = PluginConfig::default();
+    let manifest_path = manifest_dir.join("Cargo.toml");
+    if let Some(manifest) = read_table(&manifest_path)? {
+        let settings = manifest
+            .get("package")
+            .and_then(|package| package.get("metadata"))
+            .and_then(|metadata| metadata.get("bspg"));
+        if let Some(settings) = settings {
+            let settings = expect_table(&manifest_path, "package.metadata.bspg", settings)?;
+            apply_settings(&mut config, &manifest_path, settings, manifest_dir, src)?;
+        }
+    }
+    if let Some(src) = src {
+        let src = manifest_dir.join(src);
+        let config_dir = src.parent().unwrap_or(manifest_dir);
+        let config_path = config_dir.join(CONFIG_FILE_NAME);
+        if let Some(settings) = read_table(&config_path)? {
+            apply_settings(&mut config, &config_path, &settings, config_dir, Some(&src))?;
+        }
+    }
+    Ok(config)
+}
+
+/// The settings file at `path`, if there is one
+fn read_table(path: &Path) -> Result<Option<Table>, GeneratorError> {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(GeneratorError::io(error, path)),
+    };
+    source
+        .parse::<Table>()
+        .map(Some)
+        .map_err(|error| invalid(path, error.message()))
+}
+
+/// Apply the settings in `table`, then the overrides for `src` in its `files`
+/// table, and check the result so errors point at the file of `table`
+fn apply_settings(
+    config: &mut PluginConfig,
+    path: &Path,
+    table: &Table,
+    base_dir: &Path,
+    src: Option<&Path>,
+) -> Result<(), GeneratorError> {
+    for (name, value) in table.iter().filter(|(name, _)| *name != FILES_KEY) {
+        apply_setting(config, path, name, value)?;
+    }
+    if let (Some(src), Some(files)) = (src, table.get(FILES_KEY)) {
+        let files = expect_table(path, FILES_KEY, files)?;
+        for (file, overrides) in files {
+            if same_file(&base_dir.join(file), &base_dir.join(src)) {
+                let overrides = expect_table(path, &format!("{FILES_KEY}.{file}"), overrides)?;
+                for (name, value) in overrides {
+                    apply_setting(config, path, name, value)?;
+                }
+            }
+        }
+    }
+    config
+        .validate_fields()
+        .map_err(|error| invalid(path, error))
+}
+
+fn apply_setting(
+    config: &mut PluginConfig,
+    path: &Path,
+    name: &str,
+    value: &Value,
+) -> Result<(), GeneratorError> {
+    let value = match value {
+        Value::String(value) => DirectiveValue::String(value.clone()),
+        Value::Boolean(value) => DirectiveValue::Bool(*value),
+        // rustfmt options like `max_width` are numbers
+        Value::Integer(value) if name.starts_with("rustfmt.") => {
+            DirectiveValue::String(value.to_string())
+        }
+        Value::Table(table) if name == "rustfmt" => {
+            for (key, value) in table {
+                apply_setting(config, path, &format!("rustfmt.{key}"), value)?;
+            }
+            return Ok(());
+        }
+        Value::Array(values) => values
+            .iter()
+            .map(|value| value.as_str().map(ToString::to_string))
+            .collect::<Option<Vec<_>>>()
+            .map(DirectiveValue::List)
+            .ok_or_else(|| invalid(path, format!("'{name}' must be a list of strings")))?,
+        value => {
+            return Err(invalid(
+                path,
+                format!("'{name}' must be a string, found {}", value.type_str()),
+            ));
+        }
+    };
+    apply_directive(config, name, &value).map_err(|error| invalid(path, error))
+}
+
+fn expect_table<'t>(path: &Path, key: &str, value: &'t Value) -> Result<&'t Table, GeneratorError> {
+    value
+        .as_table()
+        .ok_or_else(|| invalid(path, format!("'{key}' must be a table")))
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.components().collect_vec() == b.components().collect_vec(),
+    }
+}
+
+fn invalid(path: &Path, message: impl ToString) -> GeneratorError {
+    GeneratorError::InvalidConfigFile {
+        path: path.to_path_buf(),
+        message: message.to_string(),
+    }
+}
@@ -0,0 +1,149 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+
+use indoc::indoc;
+use rstest::rstest;
+use speculoos::prelude::*;
+use tempfile::TempDir;
+
+use crate::config::manifest::{load_config, watched_paths};
+use crate::generate::GeneratorError;
+use crate::prelude::{NamingScheme, PluginName, RustfmtConfig, RustfmtFailure};
+
+fn crate_dir(manifest: &str, files: &[(&str, &str)]) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("Cargo.toml"), manifest).unwrap();
+    for (path, contents) in files {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+    dir
+}
+
+const MANIFEST: &str = indoc! {r#"
+    [package]
+    name = "game"
+
+    [package.metadata.bspg]
+    plugin_name = "game_states_plugin"
+    root_state_name = false
+    naming_scheme = "short"
+    additional_derives = ["PartialOrd", "Ord"]
+
+    [package.metadata.bspg.files."src/ui.txt"]
+    states_module_name = "ui_states"
+"#};
+
+#[rstest]
+fn test_load_config_from_manifest() {
+    let dir = crate_dir(MANIFEST, &[]);
+    let config = load_config(dir.path(), None).unwrap();
+    assert_that!(config.plugin_name).is_equal_to(PluginName::new_function("game_states_plugin"));
+    assert_that!(config.root_state_name).is_none();
+    assert_that!(config.naming_scheme).is_equal_to(NamingScheme::Short);
+    assert_that!(config.additional_derives)
+        .is_equal_to(vec![Cow::from("PartialOrd"), Cow::from("Ord")]);
+    assert_that!(config.states_module_name).is_equal_to(Cow::from("states"));
+}
+
+#[rstest]
+fn test_load_config_rustfmt_table() {
+    let settings = indoc! {r#"
+        [rustfmt]
+        edition = "2024"
+        on_failure = "error"
+        max_width = 80
+    "#};
+    let dir = crate_dir(
+        "[package]\nname = \"game\"\n",
+        &[("src/bspg.toml", settings)],
+    );
+    let config = load_config(dir.path(), Some(Path::new("src/states.txt"))).unwrap();
+    let expected = RustfmtConfig::new()
+        .with_edition("2024")
+        .with_on_failure(RustfmtFailure::Error)
+        .with_option("max_width", 80);
+    assert_that!(config.rustfmt).is_equal_to(expected);
+}
+
+#[rstest]
+fn test_load_config_without_settings_is_default() {
+    let dir = crate_dir("[package]\nname = \"game\"\n", &[]);
+    let config = load_config(dir.path(), Some(Path::new("src/states.txt"))).unwrap();
+    assert_that!(config.plugin_name).is_equal_to(PluginName::new_struct("GeneratedStatesPlugin"));
+}
+
+#[rstest]
+#[case::other_file("src/states.txt", "states", "GameState")]
+#[case::manifest_override("src/ui.txt", "ui_states", "GameState")]
+#[case::config_file_override("src/menu.txt", "states", "MenuState")]
+fn test_load_config_for_source_file(
+    #[case] src: &str,
+    #[case] states_module_name: &str,
+    #[case] root_state_name: &str,
+) {
+    let config_file = indoc! {r#"
+        root_state_name = "GameState"
+        naming_scheme = "full"
+
+        [files."menu.txt"]
+        root_state_name = "MenuState"
+    "#};
+    let dir = crate_dir(MANIFEST, &[("src/bspg.toml", config_file)]);
+    let config = load_config(dir.path(), Some(Path::new(src))).unwrap();
+    assert_that!(config.states_module_name).is_equal_to(Cow::from(states_module_name));
+    assert_that!(config.root_state_name).is_equal_to(Some(Cow::from(root_state_name)));
+    // the bspg.toml next to the source takes precedence
+    assert_that!(config.naming_scheme).is_equal_to(NamingScheme::Full);
+    assert_that!(config.plugin_name).is_equal_to(PluginName::new_function("game_states_plugin"));
+}
+
+#[rstest]
+#[case::syntax("naming_scheme = ", "string")]
+#[case::unknown_setting("colour = \"blue\"", "unknown setting: 'colour'")]
+#[case::naming_scheme("naming_scheme = \"long\"", "invalid naming scheme 'long'")]
+#[case::plugin_name("plugin_name = \"1st\"", "invalid plugin name '1st'")]
+#[case::derive("additional_derives = [\"Partial Ord\"]", "`additional_derives`")]
+#[case::derive_type("additional_derives = [1]", "must be a list of strings")]
+#[case::value_type("states_module_name = 1", "must be a string, found integer")]
+#[case::files_table("files = 1", "'files' must be a table")]
+#[case::rustfmt_failure("rustfmt.on_failure = \"panic\"", "invalid rustfmt failure 'panic'")]
+fn test_load_config_rejects_invalid_settings(#[case] settings: &str, #[case] reason: &str) {
+    let dir = crate_dir(
+        "[package]\nname = \"game\"\n",
+        &[("src/bspg.toml", settings)],
+    );
+    let error = load_config(dir.path(), Some(Path::new("src/states.txt"))).unwrap_err();
+    assert_that!(matches!(error, GeneratorError::InvalidConfigFile { .. })).is_true();
+    assert_that!(error.to_string()).contains(reason);
+    // the error points at the file with the invalid setting
+    let config_path = dir.path().join("src").join("bspg.toml");
+    assert_that!(error.path()).is_equal_to(Some(config_path.as_path()));
+}
+
+#[rstest]
+fn test_load_config_reports_unreadable_files() {
+    let dir = crate_dir("[package]\nname = \"game\"\n", &[]);
+    // a directory where the settings file would be cannot be read
+    let config_path = dir.path().join("src").join("bspg.toml");
+    fs::create_dir_all(&config_path).unwrap();
+    let error = load_config(dir.path(), Some(Path::new("src/states.txt"))).unwrap_err();
+    assert_that!(matches!(error, GeneratorError::Io { .. })).is_true();
+    assert_that!(error.path()).is_equal_to(Some(config_path.as_path()));
+}
+
+#[rstest]
+fn test_watched_paths_only_name_existing_files() {
+    let dir = crate_dir("[package]\nname = \"game\"\n", &[("src/states.txt", "")]);
+    let paths = watched_paths(dir.path(), Some(Path::new("src/states.txt")));
+    // the missing settings file is watched through its directory
+    let manifest_path = dir.path().join("Cargo.toml");
+    let src_dir = dir.path().join("src");
+    assert_that!(paths).is_equal_to(vec![manifest_path.clone(), src_dir.clone()]);
+
+    fs::write(src_dir.join("bspg.toml"), "").unwrap();
+    let paths = watched_paths(dir.path(), Some(Path::new("src/states.txt")));
+    assert_that!(paths).is_equal_to(vec![manifest_path, src_dir.join("bspg.toml")]);
+}
@@ -1,9 +1,17 @@
 use indoc::formatdoc;
 use itertools::Itertools;
 use nom::AsChar;
+use proc_macro2::TokenStream;
 
-use crate::parsing::parse_config;
-use crate::prelude::PluginConfig;
+#[cfg(feature = "rustfmt")]
+use crate::generate::diagnostics::Diagnostic;
+use crate::generate::{GeneratorError, Rendered};
+use crate::parsing::header::find_generated_regions;
+use crate::parsing::tree::StateNode;
+use crate::parsing::{Node, parse_config};
+#[cfg(feature = "rustfmt")]
+use crate::prelude::RustfmtFailure;
+use crate::prelude::{HeaderStyle, PluginConfig, RustfmtConfig};
 use crate::processing::{ProcessingError, convert_nodes_into_plugin_source};
 
 pub(crate) const REQUIRED_DERIVES: &[&str] =
@@ -18,45 +26,240 @@ pub(crate) fn get_package_info() -> String {
     format!("{pkg} v{version}")
 }
 
-pub(crate) fn generate_debug_info(src_path: &str, source: &str) -> String {
-    let lines = source.lines().map(|line| format!("// {line}")).join("\n");
-    let pkg_info = get_package_info();
-    formatdoc! {"
-        // generated by {pkg_info}
-        // src: {src_path}
-        {lines}
-    "}
+/// Header line with the hash of the generated body
+pub(crate) const HASH_PREFIX: &str = "// hash: ";
+
+/// The header of a generated file, `body` is the code below it
+pub(crate) fn generate_debug_info(
+    header_style: HeaderStyle,
+    src_path: &str,
+    source: &str,
+    body: &str,
+) -> String {
+    let hash = content_hash(body);
+    match header_style {
+        HeaderStyle::Full => {
+            let lines = source.lines().map(|line| format!("// {line}")).join("\n");
+            let pkg_info = get_package_info();
+            formatdoc! {"
+                // generated by {pkg_info}
+                // src: {src_path}
+                {HASH_PREFIX}{hash}
+                {lines}
+            "}
+        }
+        HeaderStyle::Minimal => {
+            let pkg = env!("CARGO_PKG_NAME");
+            formatdoc! {"
+                // generated by {pkg}
+                // src: {src_path}
+                {HASH_PREFIX}{hash}
+            "}
+        }
+        HeaderStyle::SourceHash => format!("{HASH_PREFIX}{hash}\n"),
+        HeaderStyle::None => String::new(),
+    }
+}
+
+/// 64-bit FNV-1a, which unlike the std hashers is the same in every Rust version
+pub(crate) fn content_hash(body: &str) -> String {
+    let hash = body.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+/// Whether the body of a generated file no longer matches the hash in its
+/// header. In a template with `// bspg:begin` regions, every region has a
+/// header of its own, the code around them is not generated. Files without a
+/// hash are never considered modified.
+pub(crate) fn is_modified_by_hand(contents: &str) -> bool {
+    match find_generated_regions(contents) {
+        Ok(regions) if !regions.is_empty() => regions
+            .into_iter()
+            .any(|region| is_body_modified(&contents[region.range])),
+        _ => is_body_modified(contents),
+    }
+}
+
+/// Whether the code below the first empty line no longer matches the hash in
+/// the comments above it
+fn is_body_modified(contents: &str) -> bool {
+    let Some((header, body)) = contents.split_once("\n\n") else {
+        return false;
+    };
+    if !header.lines().all(|line| line.starts_with("//")) {
+        return false;
+    }
+    header
+        .lines()
+        .find_map(|line| line.strip_prefix(HASH_PREFIX))
+        .is_some_and(|hash| hash.trim() != content_hash(body))
+}
+
+/// Pretty-print generated tokens in-process, independent of any installed toolchain
+pub(crate) fn format_tokens(tokens: TokenStream) -> syn::Result<String> {
+    syn::parse2(tokens).map(|file| prettyplease::unparse(&file))
 }
 
+/// Run `rustfmt` on `source`, the error is what it printed to stderr
 #[cfg(feature = "rustfmt")]
-fn try_format_source(source: &str) -> std::io::Result<String> {
-    duct::cmd!("rustfmt")
+fn try_format_source(source: &str, rustfmt: &RustfmtConfig) -> Result<String, String> {
+    let mut args: Vec<std::ffi::OsString> = Vec::new();
+    if let Some(edition) = &rustfmt.edition {
+        args.extend(["--edition".into(), edition.as_ref().into()]);
+    }
+    if let Some(config_path) = &rustfmt.config_path {
+        args.extend(["--config-path".into(), config_path.into()]);
+    }
+    if !rustfmt.options.is_empty() {
+        let options = rustfmt
+            .options
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .join(",");
+        args.extend(["--config".into(), options.into()]);
+    }
+    let output = duct::cmd("rustfmt", args)
         .stdin_bytes(source)
-        .stderr_to_stdout()
-        .read()
+        .stdout_capture()
+        .stderr_capture()
+        .unchecked()
+        .run()
+        .map_err(|error| format!("could not run rustfmt: {error}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(match stderr.trim() {
+            "" => format!("rustfmt exited with {}", output.status),
+            stderr => stderr.to_string(),
+        });
+    }
+    String::from_utf8(output.stdout).map_err(|error| error.to_string())
 }
 
-pub(crate) fn format_source<S: AsRef<str>>(source: S) -> String {
-    let source = source.as_ref();
+/// Pass the source through `rustfmt` with the `rustfmt` feature, unless it is
+/// not [`enabled`](RustfmtConfig::enabled); what happens when that fails
+/// depends on [`RustfmtConfig::on_failure`]
+pub(crate) fn format_source(
+    source: &str,
+    rustfmt: &RustfmtConfig,
+) -> Result<Rendered, GeneratorError> {
     #[cfg(feature = "rustfmt")]
-    let source = try_format_source(source).unwrap_or_else(|_| source.to_owned());
+    let formatted = rustfmt.enabled.then(|| try_format_source(source, rustfmt));
+    #[cfg(feature = "rustfmt")]
+    let (source, warnings) = match (formatted, rustfmt.on_failure) {
+        (None, _) => (source.to_owned(), Vec::new()),
+        (Some(Ok(formatted)), _) => (formatted, Vec::new()),
+        (Some(Err(message)), RustfmtFailure::Error) => {
+            return Err(GeneratorError::Rustfmt(message));
+        }
+        (Some(Err(message)), RustfmtFailure::Warn) => {
+            (source.to_owned(), vec![Diagnostic::rustfmt(message)])
+        }
+        (Some(Err(_)), RustfmtFailure::Ignore) => (source.to_owned(), Vec::new()),
+    };
     #[cfg(not(feature = "rustfmt"))]
-    let source = source.to_owned();
+    let (source, warnings) = {
+        let _ = rustfmt;
+        (source.to_owned(), Vec::new())
+    };
 
-    if source.ends_with(|c: char| c.is_newline()) {
+    let source = if source.ends_with(|c: char| c.is_newline()) {
         source
     } else {
         source + "\n"
+    };
+    Ok(Rendered { source, warnings })
+}
+
+/// Parse a complete state definition into its tree of states; trailing input
+/// is an error. Comments, including `// bspg:` directives, are kept as
+/// [`StateNode::Comment`]s.
+/// ```rust
+/// # use bevy_state_plugin_generator::prelude::*;
+/// let nodes = parse_states("Loading Ready { Menu Game }").unwrap();
+/// assert_eq!(nodes.len(), 2);
+/// assert_eq!(nodes[1].name(), Some("Ready"));
+/// assert_eq!(nodes[1].children().len(), 2);
+/// ```
+pub fn parse_states(source: &str) -> Result<Vec<StateNode>, GeneratorError> {
+    Ok(parse_nodes(source)?.iter().map(StateNode::from).collect())
+}
+
+/// [`parse_states`], keeping the nodes that borrow from `source`
+pub(crate) fn parse_nodes(source: &str) -> Result<Vec<Node<'_>>, GeneratorError> {
+    let (unparsed, nodes) =
+        parse_config(source).map_err(|error| ProcessingError::from_nom(error).locate(source))?;
+    if !unparsed.trim().is_empty() {
+        return Err(ProcessingError::parse(unparsed.trim_start())
+            .locate(source)
+            .into());
     }
+    Ok(nodes)
 }
 
 pub(crate) fn generate_state_plugin_source(
     input_source: &str,
     plugin_config: PluginConfig,
     src_path: Option<&str>,
+) -> Result<Rendered, GeneratorError> {
+    // if we're writing to a file we add a header with some information
+    if let Some(src_path) = src_path {
+        let nodes = parse_nodes(input_source)?;
+        return render_states_file(nodes, input_source, plugin_config, src_path);
+    }
+    let (unparsed, nodes) = parse_config(input_source)
+        .map_err(|error| ProcessingError::from_nom(error).locate(input_source))?;
+    let rustfmt = plugin_config.rustfmt.clone();
+    let output = convert_nodes(nodes, plugin_config, input_source)?;
+    let output = if unparsed.trim().is_empty() {
+        output
+    } else {
+        [unparsed, &output].join("\n")
+    };
+
+    format_source(&output, &rustfmt)
+}
+
+/// The contents of the file generated from the already parsed states of
+/// `input_source`, which is found at `src_path`
+pub(crate) fn render_states_file(
+    nodes: Vec<Node<'_>>,
+    input_source: &str,
+    plugin_config: PluginConfig,
+    src_path: &str,
+) -> Result<Rendered, GeneratorError> {
+    let header_style = plugin_config.header_style;
+    // the hash covers the body as it is written
+    let Rendered { source, warnings } = render_body(nodes, input_source, plugin_config)?;
+    let debug_info = generate_debug_info(header_style, src_path, input_source, &source);
+    let source = if debug_info.is_empty() {
+        source
+    } else {
+        [debug_info, source].join("\n")
+    };
+    Ok(Rendered { source, warnings })
+}
+
+/// The generated code for the states parsed from `states`, without any header
+pub(crate) fn render_body(
+    nodes: Vec<Node<'_>>,
+    states: &str,
+    plugin_config: PluginConfig,
+) -> Result<Rendered, GeneratorError> {
+    let rustfmt = plugin_config.rustfmt.clone();
+    let output = convert_nodes(nodes, plugin_config, states)?;
+    format_source(&output, &rustfmt)
+}
+
+/// Errors point into `states`, which the `nodes` were parsed from
+fn convert_nodes(
+    nodes: Vec<Node<'_>>,
+    plugin_config: PluginConfig,
+    states: &str,
 ) -> Result<String, ProcessingError> {
-    let (unparsed, nodes) = parse_config(input_source)?;
-    let mut output = convert_nodes_into_plugin_source(nodes, plugin_config)?;
+    let output = convert_nodes_into_plugin_source(nodes, plugin_config)
+        .map_err(|error| error.locate(states))?;
 
     #[cfg(test)]
     {
@@ -66,16 +269,5 @@ pub(crate) fn generate_state_plugin_source(
         assert_that!(output.matches(" mod ").collect_vec()).has_length(1);
     }
 
-    // if we're writing to a file we add a header with some information
-    output = if let Some(src_path) = src_path {
-        if !unparsed.trim().is_empty() {
-            return Err(ProcessingError::InvalidConfig(unparsed.to_string()));
-        }
-        let debug_info = generate_debug_info(src_path, input_source);
-        [debug_info, output].join("\n")
-    } else {
-        [unparsed, &output].join("\n")
-    };
-
-    Ok(format_source(output))
+    Ok(output)
 }
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests;
+
+use std::path::{Path, PathBuf};
+use std::{env, fmt};
+
+use crate::parsing::directive::DirectiveError;
+
+/// A problem that did not stop the generation, like an unknown setting
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Diagnostic {
+    /// The file the problem is in, if it was read from one
+    pub path: Option<PathBuf>,
+    /// The line of the problem, starting at 1, if it is about a single line
+    pub line: Option<usize>,
+    /// What is wrong
+    pub error: DiagnosticError,
+}
+
+/// What a [`Diagnostic`] is about
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum DiagnosticError {
+    /// A `// bspg:` directive that was not applied
+    #[error(transparent)]
+    Directive(#[from] DirectiveError),
+    /// `rustfmt` failed under [`RustfmtFailure::Warn`](crate::prelude::RustfmtFailure::Warn)
+    #[error("rustfmt failed: {0}")]
+    Rustfmt(String),
+}
+
+impl Diagnostic {
+    pub(crate) fn new(line: usize, error: DirectiveError) -> Self {
+        Self {
+            path: None,
+            line: Some(line),
+            error: error.into(),
+        }
+    }
+
+    #[cfg(any(test, feature = "rustfmt"))]
+    pub(crate) fn rustfmt(message: String) -> Self {
+        Self {
+            path: None,
+            line: None,
+            error: DiagnosticError::Rustfmt(message),
+        }
+    }
+
+    pub(crate) fn with_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_path_buf());
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.path, self.line) {
+            (Some(path), Some(line)) => write!(f, "{}:{line}: {}", path.display(), self.error),
+            (Some(path), None) => write!(f, "{}: {}", path.display(), self.error),
+            (None, Some(line)) => write!(f, "line {line}: {}", self.error),
+            (None, None) => write!(f, "{}", self.error),
+        }
+    }
+}
+
+/// Cargo only shows the output of a build script when it fails, except for
+/// these lines
+pub(crate) fn cargo_warning(diagnostic: &Diagnostic) -> String {
+    format!(
+        "cargo:warning={}",
+        diagnostic.to_string().replace('\n', " ")
+    )
+}
+
+/// Whether we are running as part of a build script
+fn in_build_script() -> bool {
+    env::var_os("OUT_DIR").is_some() && env::var_os("TARGET").is_some()
+}
+
+/// Surface diagnostics where they will be seen: as Cargo warnings from a build
+/// script, on stderr otherwise
+pub(crate) fn emit_diagnostics(diagnostics: &[Diagnostic]) {
+    let in_build_script = in_build_script();
+    for diagnostic in diagnostics {
+        if in_build_script {
+            println!("{}", cargo_warning(diagnostic));
+        } else {
+            eprintln!("warning: {diagnostic}");
+        }
+    }
+}
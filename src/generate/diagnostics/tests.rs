@@ -0,0 +1,44 @@
+use std::path::Path;
+
+use rstest::rstest;
+use speculoos::prelude::*;
+
+use crate::generate::diagnostics::{Diagnostic, cargo_warning};
+use crate::parsing::directive::DirectiveError;
+
+#[rstest]
+#[case::without_path(None, "line 3: unknown setting: 'nonsense'")]
+#[case::with_path(Some("src/states.rs"), "src/states.rs:3: unknown setting: 'nonsense'")]
+fn test_diagnostic_display(#[case] path: Option<&str>, #[case] expected: &str) {
+    let mut diagnostic = Diagnostic::new(3, DirectiveError::UnknownSetting("nonsense".into()));
+    if let Some(path) = path {
+        diagnostic = diagnostic.with_path(Path::new(path));
+    }
+    assert_that!(diagnostic.to_string()).is_equal_to(expected.to_string());
+}
+
+#[rstest]
+#[case::without_path(None, "rustfmt failed: Invalid value for `--edition`")]
+#[case::with_path(
+    Some("src/states.txt"),
+    "src/states.txt: rustfmt failed: Invalid value for `--edition`"
+)]
+fn test_diagnostic_display_without_line(#[case] path: Option<&str>, #[case] expected: &str) {
+    let mut diagnostic = Diagnostic::rustfmt("Invalid value for `--edition`".into());
+    if let Some(path) = path {
+        diagnostic = diagnostic.with_path(Path::new(path));
+    }
+    assert_that!(diagnostic.to_string()).is_equal_to(expected.to_string());
+}
+
+#[rstest]
+fn test_cargo_warning_is_a_single_line() {
+    let error = DirectiveError::UnexpectedValue {
+        name: "plugin_name".into(),
+        value: "a\nb".into(),
+    };
+    let diagnostic = Diagnostic::new(1, error).with_path(Path::new("states.rs"));
+    assert_that!(cargo_warning(&diagnostic)).is_equal_to(
+        "cargo:warning=states.rs:1: unexpected value for 'plugin_name': a b".to_string(),
+    );
+}
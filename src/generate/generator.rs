@@ -0,0 +1,566 @@
+#[cfg(test)]
+mod tests;
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::{env, fmt, fs, io};
+
+use crate::generate::core::{parse_nodes, render_states_file};
+use crate::generate::diagnostics::{Diagnostic, emit_diagnostics};
+use crate::generate::output::{GenerationOutcome, check_up_to_date};
+use crate::generate::{GeneratorError, Rendered, display_path, output_path, render_template_file,
+                      validate_output};
+use crate::parsing::Node;
+use crate::parsing::header::parse_source_header;
+use crate::prelude::{HeaderStyle, PluginConfig, RustfmtConfig};
+
+/// Generate plugins from any number of sources into any number of outputs.
+/// Every source is read and parsed once, no matter how many outputs it has, and
+/// a failing source or output does not stop the others: all errors are
+/// collected into [`GeneratorError::Batch`]. That includes outputs added
+/// before any source, see [`GeneratorError::OutputWithoutSource`].
+/// ```rust no_run
+/// use bevy_state_plugin_generator::prelude::*;
+/// fn main() {
+///   Generator::new()
+///     .header_style(HeaderStyle::Minimal)
+///     .template("src/generated_states.rs", PluginConfig::default())
+///     .source("src/states.txt")
+///     .output("src/generated_states_short.rs", NamingScheme::Short.into())
+///     .output_to_out_dir("states_full.rs", NamingScheme::Full.into())
+///     .sources_in("src/states", "txt", PluginConfig::default())
+///     .run()
+///     .expect("Failed to generate plugins!");
+/// }
+/// ```
+/// Sources and outputs do not have to be files:
+/// ```rust
+/// use bevy_state_plugin_generator::prelude::*;
+/// let mut generated = Vec::new();
+/// Generator::new()
+///   .cargo(false)
+///   .source_str("states.txt", "Loading Ready { Menu Game }")
+///   .output_to_writer(&mut generated, PluginConfig::default())
+///   .run()
+///   .expect("Failed to generate plugin!");
+/// assert!(String::from_utf8(generated).unwrap().contains("GeneratedStatesPlugin"));
+/// ```
+/// It is not `Clone`, because the readers and writers it holds cannot be cloned.
+#[derive(Debug)]
+pub struct Generator<'a> {
+    entries: Vec<Entry<'a>>,
+    options: Options,
+}
+
+/// Settings for every output of a [`Generator`]
+#[derive(Debug)]
+struct Options {
+    cargo: bool,
+    check: bool,
+    header_style: Option<HeaderStyle>,
+    rustfmt: Option<RustfmtConfig>,
+    format: Option<bool>,
+}
+
+#[derive(Debug)]
+enum Entry<'a> {
+    /// A state definition and the files generated from it
+    Source {
+        name: PathBuf,
+        input: Input<'a>,
+        outputs: Vec<Output<'a>>,
+    },
+    /// Every file with the extension in a directory, written to their `// bspg:output`
+    Directory {
+        path: PathBuf,
+        extension: String,
+        config: PluginConfig,
+    },
+    /// A template that is updated in place
+    Template { path: PathBuf, config: PluginConfig },
+    /// An output that was added before any source, reported when running
+    OutputWithoutSource { output: PathBuf },
+}
+
+enum Input<'a> {
+    Path(PathBuf),
+    Text(String),
+    Reader(Box<dyn Read + 'a>),
+}
+
+#[derive(Debug)]
+struct Output<'a> {
+    destination: Destination<'a>,
+    config: PluginConfig,
+}
+
+enum Destination<'a> {
+    Path(PathBuf),
+    /// The `// bspg:output` of the source, or next to it
+    FromSource,
+    /// A file name in `$OUT_DIR`
+    OutDir(PathBuf),
+    Writer(Box<dyn Write + 'a>),
+}
+
+impl fmt::Debug for Input<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Path(path) => f.debug_tuple("Path").field(path).finish(),
+            Self::Text(text) => f.debug_tuple("Text").field(text).finish(),
+            Self::Reader(_) => f.write_str("Reader"),
+        }
+    }
+}
+
+impl fmt::Debug for Destination<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Path(path) => f.debug_tuple("Path").field(path).finish(),
+            Self::FromSource => f.write_str("FromSource"),
+            Self::OutDir(file_name) => f.debug_tuple("OutDir").field(file_name).finish(),
+            Self::Writer(_) => f.write_str("Writer"),
+        }
+    }
+}
+
+impl Default for Generator<'_> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            options: Options {
+                cargo: true,
+                check: false,
+                header_style: None,
+                rustfmt: None,
+                format: None,
+            },
+        }
+    }
+}
+
+impl<'a> Generator<'a> {
+    /// A generator without any sources
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Print `cargo:rerun-if-changed` for every file that is read and report
+    /// warnings as `cargo:warning`, as a build script should. On by default;
+    /// when off, warnings are only returned in the [`GenerationOutcome`]s.
+    pub fn cargo(mut self, cargo: bool) -> Self {
+        self.options.cargo = cargo;
+        self
+    }
+
+    /// Compare every output with the file on disk instead of writing it, like
+    /// [`check_plugin`](crate::prelude::check_plugin). Outputs that differ fail
+    /// with [`GeneratorError::OutOfDate`]; writers are still written to.
+    pub fn check(mut self, check: bool) -> Self {
+        self.options.check = check;
+        self
+    }
+
+    /// Use this header style for every output, instead of the one of its config
+    pub fn header_style(mut self, header_style: HeaderStyle) -> Self {
+        self.options.header_style = Some(header_style);
+        self
+    }
+
+    /// Run `rustfmt` like this for every output, instead of like its config says
+    pub fn rustfmt(mut self, rustfmt: RustfmtConfig) -> Self {
+        self.options.rustfmt = Some(rustfmt);
+        self
+    }
+
+    /// Run `rustfmt` on every output or on none of them, instead of like
+    /// [`RustfmtConfig::enabled`] says. This also applies to [`Generator::rustfmt`].
+    pub fn format(mut self, format: bool) -> Self {
+        self.options.format = Some(format);
+        self
+    }
+
+    /// Add a state definition, followed by its outputs
+    pub fn source(self, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        self.push_source(path.clone(), Input::Path(path))
+    }
+
+    /// Add a state definition that is not read from a file. The `name` stands
+    /// in for its path, in the header and when writing to its `// bspg:output`.
+    pub fn source_str(self, name: impl AsRef<Path>, source: impl Into<String>) -> Self {
+        self.push_source(name.as_ref().to_path_buf(), Input::Text(source.into()))
+    }
+
+    /// Like [`Generator::source_str`], read when the generator runs
+    pub fn source_reader(self, name: impl AsRef<Path>, reader: impl Read + 'a) -> Self {
+        self.push_source(name.as_ref().to_path_buf(), Input::Reader(Box::new(reader)))
+    }
+
+    /// Generate a plugin from the last source and write it to `dst`
+    pub fn output(self, dst: impl AsRef<Path>, plugin_config: PluginConfig) -> Self {
+        let destination = Destination::Path(dst.as_ref().to_path_buf());
+        self.push_output(destination, plugin_config)
+    }
+
+    /// Generate a plugin from the last source and write it to the path of its
+    /// `// bspg:output` directive, like
+    /// [`generate_plugin_from_source`](crate::prelude::generate_plugin_from_source)
+    pub fn output_from_source(self, plugin_config: PluginConfig) -> Self {
+        self.push_output(Destination::FromSource, plugin_config)
+    }
+
+    /// Generate a plugin from the last source and write it to `$OUT_DIR`, like
+    /// [`generate_plugin_to_out_dir`](crate::prelude::generate_plugin_to_out_dir)
+    pub fn output_to_out_dir(
+        self,
+        file_name: impl AsRef<Path>,
+        plugin_config: PluginConfig,
+    ) -> Self {
+        let destination = Destination::OutDir(file_name.as_ref().to_path_buf());
+        self.push_output(destination, plugin_config)
+    }
+
+    /// Generate a plugin from the last source and write it to `writer`. The
+    /// [`GenerationOutcome::path`] of such an output is empty.
+    pub fn output_to_writer(self, writer: impl Write + 'a, plugin_config: PluginConfig) -> Self {
+        self.push_output(Destination::Writer(Box::new(writer)), plugin_config)
+    }
+
+    /// Add every file with the `extension`, like `"txt"`, directly inside `dir`,
+    /// each written to the path of its `// bspg:output` directive
+    pub fn sources_in(
+        mut self,
+        dir: impl AsRef<Path>,
+        extension: impl Into<String>,
+        plugin_config: PluginConfig,
+    ) -> Self {
+        self.entries.push(Entry::Directory {
+            path: dir.as_ref().to_path_buf(),
+            extension: extension.into(),
+            config: plugin_config,
+        });
+        self
+    }
+
+    /// Update a template in place, like [`update_template`](crate::prelude::update_template)
+    pub fn template(mut self, path: impl AsRef<Path>, plugin_config: PluginConfig) -> Self {
+        self.entries.push(Entry::Template {
+            path: path.as_ref().to_path_buf(),
+            config: plugin_config,
+        });
+        self
+    }
+
+    fn push_source(mut self, name: PathBuf, input: Input<'a>) -> Self {
+        self.entries.push(Entry::Source {
+            name,
+            input,
+            outputs: Vec::new(),
+        });
+        self
+    }
+
+    fn push_output(mut self, destination: Destination<'a>, config: PluginConfig) -> Self {
+        let output = Output {
+            destination,
+            config,
+        };
+        match self.entries.last_mut() {
+            Some(Entry::Source { outputs, .. }) => outputs.push(output),
+            _ => self.entries.push(Entry::OutputWithoutSource {
+                output: output.destination.path(),
+            }),
+        }
+        self
+    }
+
+    /// Generate and write all outputs, in the order they were added
+    pub fn run(self) -> Result<Vec<GenerationOutcome>, GeneratorError> {
+        let (outcomes, errors) = self.run_all();
+        if errors.is_empty() {
+            Ok(outcomes)
+        } else {
+            Err(GeneratorError::Batch { errors })
+        }
+    }
+
+    /// Run a generator with a single output, failing with its error as is
+    pub(crate) fn run_single(self) -> Result<GenerationOutcome, GeneratorError> {
+        let (mut outcomes, errors) = self.run_all();
+        match errors.into_iter().next() {
+            Some((_, error)) => Err(error),
+            None => Ok(outcomes.pop().expect("a generator with a single output")),
+        }
+    }
+
+    fn run_all(self) -> (Vec<GenerationOutcome>, Vec<(PathBuf, GeneratorError)>) {
+        let Self { entries, options } = self;
+        let mut outcomes = Vec::new();
+        let mut errors = Vec::new();
+        for entry in entries {
+            let sources = match entry {
+                Entry::Source {
+                    name,
+                    input,
+                    outputs,
+                } => vec![(name, input, outputs)],
+                Entry::Directory {
+                    path,
+                    extension,
+                    config,
+                } => {
+                    options.rerun_if_changed(&path);
+                    match states_files(&path, &extension) {
+                        Ok(paths) => paths
+                            .into_iter()
+                            .map(|path| {
+                                let output = Output {
+                                    destination: Destination::FromSource,
+                                    config: config.clone(),
+                                };
+                                (path.clone(), Input::Path(path), vec![output])
+                            })
+                            .collect(),
+                        Err(error) => {
+                            errors.push((path.clone(), GeneratorError::io(error, &path)));
+                            continue;
+                        }
+                    }
+                }
+                Entry::Template { path, config } => {
+                    match options.update_template(&path, config) {
+                        Ok(outcome) => outcomes.push(outcome),
+                        Err(error) => errors.push((path, error)),
+                    }
+                    continue;
+                }
+                Entry::OutputWithoutSource { output } => {
+                    let error = GeneratorError::OutputWithoutSource {
+                        output: output.clone(),
+                    };
+                    errors.push((output, error));
+                    continue;
+                }
+            };
+            for (name, input, outputs) in sources {
+                match options.generate_outputs(&name, input, outputs) {
+                    Ok(results) => {
+                        for result in results {
+                            match result {
+                                Ok(outcome) => outcomes.push(outcome),
+                                Err(error) => errors.push(*error),
+                            }
+                        }
+                    }
+                    Err(error) => errors.push((name, error)),
+                }
+            }
+        }
+        (outcomes, errors)
+    }
+}
+
+type OutputResult = Result<GenerationOutcome, Box<(PathBuf, GeneratorError)>>;
+
+impl Options {
+    fn rerun_if_changed(&self, path: &Path) {
+        if self.cargo {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+
+    fn emit(&self, warnings: &[Diagnostic]) {
+        if self.cargo {
+            emit_diagnostics(warnings);
+        }
+    }
+
+    fn configure(&self, mut plugin_config: PluginConfig) -> PluginConfig {
+        if let Some(header_style) = self.header_style {
+            plugin_config.header_style = header_style;
+        }
+        if let Some(rustfmt) = &self.rustfmt {
+            plugin_config.rustfmt = rustfmt.clone();
+        }
+        if let Some(format) = self.format {
+            plugin_config.rustfmt.enabled = format;
+        }
+        plugin_config
+    }
+
+    fn update_template(
+        &self,
+        path: &Path,
+        plugin_config: PluginConfig,
+    ) -> Result<GenerationOutcome, GeneratorError> {
+        self.rerun_if_changed(path);
+        let plugin_config = self.configure(plugin_config);
+        let force = plugin_config.force;
+        let rendered = render_template_file(path, plugin_config)?;
+        self.emit(&rendered.warnings);
+        self.finish(rendered, path, force)
+    }
+
+    /// Write the rendered file to `path`, or only compare it in check mode
+    fn finish(
+        &self,
+        rendered: Rendered,
+        path: &Path,
+        force: bool,
+    ) -> Result<GenerationOutcome, GeneratorError> {
+        if !self.check {
+            return rendered.write(path, force);
+        }
+        check_up_to_date(path, &rendered.source)?;
+        Ok(GenerationOutcome {
+            changed: false,
+            path: path.to_path_buf(),
+            warnings: rendered.warnings,
+        })
+    }
+
+    /// Read and parse the source once, then generate and write each of its
+    /// outputs. Fails as a whole if the source cannot be read or parsed.
+    fn generate_outputs(
+        &self,
+        src: &Path,
+        input: Input<'_>,
+        outputs: Vec<Output<'_>>,
+    ) -> Result<Vec<OutputResult>, GeneratorError> {
+        let source = match input {
+            Input::Path(path) => {
+                self.rerun_if_changed(&path);
+                fs::read_to_string(&path).map_err(|error| GeneratorError::io(error, &path))?
+            }
+            Input::Text(source) => source,
+            Input::Reader(mut reader) => {
+                let mut source = String::new();
+                reader
+                    .read_to_string(&mut source)
+                    .map_err(|error| GeneratorError::io(error, src))?;
+                source
+            }
+        };
+        // the states are the same for every output, only the directives differ
+        let header = parse_source_header(&source, &mut PluginConfig::default())
+            .map_err(|error| error.in_file(src))?;
+        let nodes = parse_nodes(&header.states)
+            .map_err(|error| error.relocate(&header.source_map).in_file(src))?;
+        let src_display = display_path(src, env::var_os("CARGO_MANIFEST_DIR"));
+        let source = ParsedSource {
+            path: src,
+            display: &src_display,
+            source: &source,
+            nodes: &nodes,
+        };
+        Ok(outputs
+            .into_iter()
+            .map(|output| self.generate_output(&source, output))
+            .collect())
+    }
+
+    fn generate_output(&self, src: &ParsedSource<'_>, output: Output<'_>) -> OutputResult {
+        let Output {
+            destination,
+            config,
+        } = output;
+        let plugin_config = self.configure(config);
+        let force = plugin_config.force;
+        let error_path = match &destination {
+            Destination::Path(dst) => dst.clone(),
+            _ => src.path.to_path_buf(),
+        };
+        let (rendered, output) = src
+            .render(plugin_config)
+            .map_err(|error| Box::new((error_path.clone(), error)))?;
+        self.emit(&rendered.warnings);
+        let dst = match destination {
+            Destination::Path(dst) => Ok(dst),
+            Destination::FromSource => output_path(src.path, output.as_deref())
+                .map_err(|error| GeneratorError::io(error, src.path)),
+            Destination::OutDir(file_name) => out_dir()
+                .map(|out_dir| out_dir.join(&file_name))
+                .map_err(|error| GeneratorError::io(error, &file_name)),
+            Destination::Writer(mut writer) => {
+                return writer
+                    .write_all(rendered.source.as_bytes())
+                    .map(|()| GenerationOutcome {
+                        changed: true,
+                        path: PathBuf::new(),
+                        warnings: rendered.warnings,
+                    })
+                    .map_err(|error| {
+                        let error = GeneratorError::io(error, &error_path);
+                        Box::new((error_path, error))
+                    });
+            }
+        }
+        .map_err(|error| Box::new((error_path, error)))?;
+        self.finish(rendered, &dst, force)
+            .map_err(|error| Box::new((dst, error)))
+    }
+}
+
+impl Destination<'_> {
+    /// The path that is known before generating, empty if there is none
+    fn path(&self) -> PathBuf {
+        match self {
+            Self::Path(path) | Self::OutDir(path) => path.clone(),
+            Self::FromSource | Self::Writer(_) => PathBuf::new(),
+        }
+    }
+}
+
+/// A state definition with its states parsed, shared by all of its outputs
+struct ParsedSource<'s> {
+    path: &'s Path,
+    display: &'s str,
+    source: &'s str,
+    nodes: &'s [Node<'s>],
+}
+
+impl ParsedSource<'_> {
+    /// Apply the directives of the source to the config of an output and render
+    /// it, along with the `// bspg:output` of the source
+    fn render(
+        &self,
+        mut plugin_config: PluginConfig,
+    ) -> Result<(Rendered, Option<String>), GeneratorError> {
+        let header = parse_source_header(self.source, &mut plugin_config)
+            .map_err(|error| error.in_file(self.path))?;
+        let mut rendered = render_states_file(
+            self.nodes.to_vec(),
+            &header.states,
+            plugin_config,
+            self.display,
+        )
+        .map_err(|error| error.relocate(&header.source_map).in_file(self.path))?;
+        validate_output(&rendered.source).map_err(|error| error.in_file(self.path))?;
+        rendered.warnings.splice(0..0, header.warnings);
+        Ok((rendered.found_in(self.path), header.output))
+    }
+}
+
+/// `$OUT_DIR`, which cargo only sets for build scripts
+fn out_dir() -> io::Result<PathBuf> {
+    env::var_os("OUT_DIR").map(PathBuf::from).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "OUT_DIR is not set, generating into it must happen in a build script",
+        )
+    })
+}
+
+/// The files with the `extension` directly inside `dir`, sorted by path
+fn states_files(dir: &Path, extension: &str) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == extension) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
@@ -0,0 +1,325 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rstest::rstest;
+use speculoos::prelude::*;
+use tempfile::TempDir;
+
+use crate::generate::generator::Generator;
+use crate::generate::{GeneratorError, check_plugin};
+use crate::prelude::{HeaderStyle, NamingScheme, PluginConfig};
+
+fn write_source(dir: &Path, name: &str, contents: &str) {
+    fs::write(dir.join(name), contents).unwrap();
+}
+
+#[rstest]
+fn test_generator_writes_every_output_of_a_source() {
+    let dir = TempDir::new().unwrap();
+    write_source(dir.path(), "states.txt", "Loading Ready { Menu Game }");
+    let src = dir.path().join("states.txt");
+    let schemes = [NamingScheme::None, NamingScheme::Short, NamingScheme::Full];
+
+    let generator = schemes
+        .into_iter()
+        .fold(Generator::new().source(&src), |generator, scheme| {
+            let dst = dir.path().join(format!("states_{}.rs", scheme.tag()));
+            generator.output(dst, scheme.into())
+        });
+    let outcomes = generator.run().unwrap();
+
+    assert_that!(outcomes).has_length(3);
+    for (outcome, scheme) in outcomes.iter().zip(schemes) {
+        assert_that!(outcome.changed).is_true();
+        assert_that!(outcome.path)
+            .is_equal_to(dir.path().join(format!("states_{}.rs", scheme.tag())));
+        assert_that!(check_plugin(&src, &outcome.path, scheme.into())).is_ok();
+    }
+}
+
+#[rstest]
+fn test_generator_applies_the_directives_to_every_output() {
+    let dir = TempDir::new().unwrap();
+    write_source(
+        dir.path(),
+        "states.txt",
+        "// bspg:plugin_name MyPlugin\nA B",
+    );
+    let src = dir.path().join("states.txt");
+
+    Generator::new()
+        .source(&src)
+        .output(dir.path().join("short.rs"), NamingScheme::Short.into())
+        .output_from_source(PluginConfig::default())
+        .run()
+        .unwrap();
+
+    for generated in ["short.rs", "states.rs"] {
+        let contents = fs::read_to_string(dir.path().join(generated)).unwrap();
+        assert_that!(contents.as_str()).contains("pub struct MyPlugin;");
+    }
+}
+
+#[rstest]
+fn test_generator_sources_in_directory() {
+    let dir = TempDir::new().unwrap();
+    write_source(dir.path(), "ui.states", "Hud Menu");
+    write_source(
+        dir.path(),
+        "game.states",
+        "// bspg:output \"out/game.rs\"\nPaused Running",
+    );
+    write_source(dir.path(), "notes.md", "Not A States File");
+    fs::create_dir(dir.path().join("out")).unwrap();
+
+    let outcomes = Generator::new()
+        .sources_in(dir.path(), "states", PluginConfig::default())
+        .run()
+        .unwrap();
+
+    let paths = outcomes
+        .into_iter()
+        .map(|outcome| outcome.path)
+        .collect::<Vec<_>>();
+    assert_that!(paths).is_equal_to(vec![
+        dir.path().join("out").join("game.rs"),
+        dir.path().join("ui.rs"),
+    ]);
+    assert_that!(dir.path().join("notes.rs").exists()).is_false();
+}
+
+#[rstest]
+fn test_generator_collects_all_errors() {
+    let dir = TempDir::new().unwrap();
+    write_source(dir.path(), "good.txt", "A B");
+    write_source(dir.path(), "bad.txt", "A { B");
+    let missing = dir.path().join("missing.txt");
+    let bad = dir.path().join("bad.txt");
+
+    let result = Generator::new()
+        .source(&missing)
+        .output(dir.path().join("missing.rs"), PluginConfig::default())
+        .source(&bad)
+        .output(dir.path().join("bad.rs"), PluginConfig::default())
+        .source(dir.path().join("good.txt"))
+        .output(dir.path().join("good.rs"), PluginConfig::default())
+        .run();
+
+    let Err(GeneratorError::Batch { errors }) = result else {
+        panic!("expected a batch error, got {result:?}");
+    };
+    let paths = errors
+        .iter()
+        .map(|(path, _)| path.clone())
+        .collect::<Vec<_>>();
+    assert_that!(paths).is_equal_to(vec![missing, bad]);
+    assert_that!(matches!(errors[0].1, GeneratorError::Io { .. })).is_true();
+    assert_that!(matches!(errors[1].1, GeneratorError::Processing { .. })).is_true();
+    assert_that!(dir.path().join("good.rs").exists()).is_true();
+}
+
+#[rstest]
+fn test_generator_reports_the_failing_output() {
+    let dir = TempDir::new().unwrap();
+    write_source(dir.path(), "states.txt", "// bspg:naming_scheme long\nA B");
+    let strict = PluginConfig::default().with_strict(true);
+
+    let result = Generator::new()
+        .source(dir.path().join("states.txt"))
+        .output(dir.path().join("lenient.rs"), PluginConfig::default())
+        .output(dir.path().join("strict.rs"), strict)
+        .run();
+
+    let Err(GeneratorError::Batch { errors }) = result else {
+        panic!("expected a batch error, got {result:?}");
+    };
+    assert_that!(errors).has_length(1);
+    assert_that!(errors[0].0).is_equal_to(dir.path().join("strict.rs"));
+    assert_that!(dir.path().join("lenient.rs").exists()).is_true();
+}
+
+#[rstest]
+fn test_generator_output_without_source() {
+    let dir = TempDir::new().unwrap();
+    let mut generated = Vec::new();
+
+    let result = Generator::new()
+        .cargo(false)
+        .output(dir.path().join("orphan.rs"), PluginConfig::default())
+        .output_to_writer(&mut generated, PluginConfig::default())
+        .source_str("states.txt", "A B")
+        .output(dir.path().join("states.rs"), PluginConfig::default())
+        .run();
+
+    let Err(GeneratorError::Batch { errors }) = result else {
+        panic!("expected a batch error, got {result:?}");
+    };
+    let paths = errors
+        .iter()
+        .map(|(path, _)| path.clone())
+        .collect::<Vec<_>>();
+    assert_that!(paths).is_equal_to(vec![dir.path().join("orphan.rs"), PathBuf::new()]);
+    for (_, error) in &errors {
+        assert_that!(matches!(error, GeneratorError::OutputWithoutSource { .. })).is_true();
+    }
+    assert_that!(errors[1].1.to_string())
+        .is_equal_to("An output has no source, add one before its outputs".to_string());
+    assert_that!(dir.path().join("orphan.rs").exists()).is_false();
+    assert_that!(dir.path().join("states.rs").exists()).is_true();
+}
+
+#[rstest]
+fn test_generator_io_errors_name_the_file() {
+    let result = Generator::new()
+        .cargo(false)
+        .source_str("states.rs", "A B")
+        .output_from_source(PluginConfig::default())
+        .run();
+
+    let Err(GeneratorError::Batch { errors }) = result else {
+        panic!("expected a batch error, got {result:?}");
+    };
+    let GeneratorError::Io { path, .. } = &errors[0].1 else {
+        panic!("expected an io error, got {:?}", errors[0].1);
+    };
+    assert_that!(path.as_deref()).is_equal_to(Some(Path::new("states.rs")));
+}
+
+#[rstest]
+fn test_generator_debug() {
+    let mut generated = Vec::new();
+    let generator = Generator::new()
+        .source_reader("states.txt", "A B".as_bytes())
+        .output_to_writer(&mut generated, PluginConfig::default());
+    let debug = format!("{generator:?}");
+    assert_that!(debug.as_str()).contains("\"states.txt\"");
+    assert_that!(debug.as_str()).contains("Reader");
+    assert_that!(debug.as_str()).contains("Writer");
+}
+
+#[cfg(feature = "rustfmt")]
+#[rstest]
+#[case::on(true)]
+#[case::off(false)]
+fn test_generator_format(#[case] format: bool) {
+    let mut generated = Vec::new();
+    let rustfmt = crate::prelude::RustfmtConfig::disabled().with_option("hard_tabs", true);
+
+    Generator::new()
+        .cargo(false)
+        .rustfmt(rustfmt)
+        .format(format)
+        .source_str("states.txt", "A B")
+        .output_to_writer(&mut generated, PluginConfig::default())
+        .run()
+        .unwrap();
+
+    let generated = String::from_utf8(generated).unwrap();
+    assert_that!(generated.contains('\t')).is_equal_to(format);
+}
+
+#[rstest]
+fn test_generator_from_str_to_writer() {
+    let mut generated = Vec::new();
+    let outcomes = Generator::new()
+        .cargo(false)
+        .source_str("states.txt", "// bspg:plugin_name MyPlugin\nA B")
+        .output_to_writer(&mut generated, PluginConfig::default())
+        .run()
+        .unwrap();
+
+    assert_that!(outcomes).has_length(1);
+    assert_that!(outcomes[0].path).is_equal_to(PathBuf::new());
+    let generated = String::from_utf8(generated).unwrap();
+    assert_that!(generated.as_str()).contains("pub struct MyPlugin;");
+    assert_that!(generated.as_str()).contains("// src: states.txt");
+}
+
+#[rstest]
+fn test_generator_from_reader_to_source_output() {
+    let dir = TempDir::new().unwrap();
+    let name = dir.path().join("states.txt");
+
+    Generator::new()
+        .cargo(false)
+        .source_reader(&name, "A B".as_bytes())
+        .output_from_source(PluginConfig::default())
+        .run()
+        .unwrap();
+
+    assert_that!(dir.path().join("states.rs").exists()).is_true();
+    assert_that!(name.exists()).is_false();
+}
+
+#[rstest]
+fn test_generator_header_style_overrides_every_output() {
+    let mut none = Vec::new();
+    let mut full = Vec::new();
+    Generator::new()
+        .cargo(false)
+        .header_style(HeaderStyle::None)
+        .source_str("states.txt", "A B")
+        .output_to_writer(&mut none, PluginConfig::default())
+        .output_to_writer(
+            &mut full,
+            PluginConfig::default().with_header_style(HeaderStyle::Full),
+        )
+        .run()
+        .unwrap();
+
+    for generated in [none, full] {
+        let generated = String::from_utf8(generated).unwrap();
+        assert_that!(generated.as_str()).does_not_contain("bevy_state_plugin_generator");
+    }
+}
+
+#[rstest]
+fn test_generator_updates_templates() {
+    let dir = TempDir::new().unwrap();
+    write_source(
+        dir.path(),
+        "states.rs",
+        "// bspg:plugin_name MyPlugin\n// A B\n",
+    );
+    let path = dir.path().join("states.rs");
+
+    let outcomes = Generator::new()
+        .cargo(false)
+        .template(&path, PluginConfig::default())
+        .run()
+        .unwrap();
+
+    assert_that!(outcomes).has_length(1);
+    assert_that!(outcomes[0].changed).is_true();
+    let contents = fs::read_to_string(&path).unwrap();
+    assert_that!(contents.as_str()).contains("pub struct MyPlugin;");
+}
+
+#[rstest]
+fn test_generator_check_compares_without_writing() {
+    let dir = TempDir::new().unwrap();
+    write_source(dir.path(), "states.txt", "Loading Ready");
+    let src = dir.path().join("states.txt");
+    let dst = dir.path().join("states.rs");
+    let generator = || {
+        Generator::new()
+            .cargo(false)
+            .check(true)
+            .source(&src)
+            .output(&dst, PluginConfig::default())
+    };
+
+    let error = generator().run_single().unwrap_err();
+    assert_that!(matches!(error, GeneratorError::OutOfDate { .. })).is_true();
+    assert_that!(dst.exists()).is_false();
+
+    Generator::new()
+        .cargo(false)
+        .source(&src)
+        .output(&dst, PluginConfig::default())
+        .run_single()
+        .unwrap();
+    let outcome = generator().run_single().unwrap();
+    assert_that!(outcome.changed).is_false();
+    assert_that!(outcome.path).is_equal_to(dst);
+}
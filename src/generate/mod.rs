@@ -1,71 +1,295 @@
 pub(crate) mod core;
+pub(crate) mod diagnostics;
+pub(crate) mod generator;
+pub(crate) mod output;
+#[cfg(feature = "fancy-diagnostics")]
+pub(crate) mod report;
 #[cfg(test)]
 mod tests;
 
-use std::path::Path;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::{fs, io};
 
 use itertools::{Itertools, concat};
+use proc_macro2::TokenStream;
 
-use crate::generate::core::generate_state_plugin_source;
-use crate::parsing::header::parse_template_header;
-use crate::prelude::PluginConfig;
-use crate::processing::ProcessingError;
+use crate::generate::core::{HASH_PREFIX, content_hash, generate_state_plugin_source, parse_nodes,
+                            render_body};
+use crate::generate::diagnostics::Diagnostic;
+use crate::generate::generator::Generator;
+use crate::generate::output::{GenerationOutcome, write_if_changed};
+use crate::parsing::directive::DirectiveError;
+use crate::parsing::header::{DEFAULT_REGION, GeneratedRegion, TemplateBlock, directive_error,
+                             find_generated_regions, has_tagged_blocks, parse_source_header,
+                             parse_template_blocks, parse_template_header};
+use crate::parsing::printer::print_nodes;
+use crate::parsing::span::{SourceMap, Span};
+use crate::prelude::{HeaderStyle, PluginConfig};
+use crate::processing::{ProcessingError, convert_nodes_into_plugin_tokens};
 
 /// The kinds of errors that can occur
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum GeneratorError {
     /// A fs-related error occurred
-    #[error("Io Error: {0}")]
-    Io(#[from] io::Error),
+    #[error("Io Error: {}{error}", location(path.as_deref(), None))]
+    Io {
+        /// The file that could not be read or written, if it is known
+        path: Option<PathBuf>,
+        /// What went wrong
+        #[source]
+        error: io::Error,
+    },
     /// The content is wrong
-    #[error("Processing Error: {0}")]
-    Processing(#[from] ProcessingError),
+    #[error("Processing Error: {}{error}", location(path.as_deref(), error.span()))]
+    Processing {
+        /// The file with the state definition, if it was read from one
+        path: Option<PathBuf>,
+        /// What is wrong with it
+        #[source]
+        error: ProcessingError,
+    },
+    /// A generated file does not match what would be generated now
+    #[error("{} is out of date:\n{diff}", path.display())]
+    OutOfDate {
+        /// The stale file
+        path: PathBuf,
+        /// Unified diff from the file on disk to the expected contents
+        diff: String,
+    },
+    /// A `// bspg:` directive was rejected, see [`PluginConfig::strict`]
+    #[error("Directive Error on line {}{}: {error}", span.line, of_file(path.as_deref()))]
+    Directive {
+        /// The file with the directive, if it was read from one
+        path: Option<PathBuf>,
+        /// The directive, in the file or string it was read from; its
+        /// [`line`](Span::line) starts at 1
+        span: Span,
+        /// What is wrong with it
+        error: Box<DirectiveError>,
+    },
+    /// A generated file was edited since it was written, see [`PluginConfig::force`]
+    #[error("{} was modified by hand, use `force` to overwrite it", path.display())]
+    ModifiedByHand {
+        /// The generated file
+        path: PathBuf,
+    },
+    /// `rustfmt` failed under [`RustfmtFailure::Error`](crate::prelude::RustfmtFailure::Error)
+    #[error("Rustfmt Error: {0}")]
+    Rustfmt(String),
+    /// Some sources or outputs of a [`Generator`](crate::prelude::Generator) failed
+    #[error("{} of the generated files failed:{}", errors.len(), display_errors(errors))]
+    Batch {
+        /// The source or output that failed, with what went wrong
+        errors: Vec<(PathBuf, GeneratorError)>,
+    },
+    /// A settings file could not be read as a [`PluginConfig`]
+    #[error("Invalid configuration in {}: {message}", path.display())]
+    InvalidConfigFile {
+        /// The `Cargo.toml` or `bspg.toml`
+        path: PathBuf,
+        /// What is wrong with it
+        message: String,
+    },
+    /// An output was added to a [`Generator`](crate::prelude::Generator) before any source
+    #[error("{} has no source, add one before its outputs", display_output(output))]
+    OutputWithoutSource {
+        /// Where the output would have been written, empty for a writer or
+        /// the `// bspg:output` of the source
+        output: PathBuf,
+    },
+}
+
+impl From<io::Error> for GeneratorError {
+    fn from(error: io::Error) -> Self {
+        Self::Io { path: None, error }
+    }
+}
+
+impl From<ProcessingError> for GeneratorError {
+    fn from(error: ProcessingError) -> Self {
+        Self::Processing { path: None, error }
+    }
+}
+
+impl GeneratorError {
+    /// The remainder of the input where parsing stopped, if parsing failed.
+    /// It is always a suffix of the source that was passed in.
+    /// ```rust
+    /// # use bevy_state_plugin_generator::prelude::*;
+    /// let config = PluginConfig::default();
+    /// let error = generate_tokens("Ready { Menu } ; Exiting", config).unwrap_err();
+    /// assert_eq!(error.unparsed_input(), Some("; Exiting"));
+    /// ```
+    pub fn unparsed_input(&self) -> Option<&str> {
+        match self {
+            Self::Processing { error, .. } => error.unparsed_input(),
+            Self::Io { .. }
+            | Self::OutOfDate { .. }
+            | Self::Directive { .. }
+            | Self::ModifiedByHand { .. }
+            | Self::Rustfmt(_)
+            | Self::Batch { .. }
+            | Self::InvalidConfigFile { .. }
+            | Self::OutputWithoutSource { .. } => None,
+        }
+    }
+
+    /// The file the error is about, if it is known
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::Io { path, .. }
+            | Self::Processing { path, .. }
+            | Self::Directive { path, .. } => path.as_deref(),
+            Self::OutOfDate { path, .. }
+            | Self::ModifiedByHand { path }
+            | Self::InvalidConfigFile { path, .. } => Some(path),
+            Self::Rustfmt(_) | Self::Batch { .. } | Self::OutputWithoutSource { .. } => None,
+        }
+    }
+
+    /// Where the problem is in the source of the states, or the directive
+    /// that was rejected
+    /// ```rust
+    /// # use bevy_state_plugin_generator::prelude::*;
+    /// let config = PluginConfig::default().with_naming_scheme(NamingScheme::None);
+    /// let error = generate_from_str("Menu\nReady { Menu }", &config).unwrap_err();
+    /// let span = error.span().unwrap();
+    /// assert_eq!((span.line, span.column), (2, 9));
+    /// assert_eq!(error.node_path(), Some("GameState.Ready.Menu"));
+    /// ```
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::Processing { error, .. } => error.span(),
+            Self::Directive { span, .. } => Some(*span),
+            Self::Io { .. }
+            | Self::OutOfDate { .. }
+            | Self::ModifiedByHand { .. }
+            | Self::Rustfmt(_)
+            | Self::Batch { .. }
+            | Self::InvalidConfigFile { .. }
+            | Self::OutputWithoutSource { .. } => None,
+        }
+    }
+
+    /// The state the problem is about, e.g. `Game.Ready.Menu`
+    pub fn node_path(&self) -> Option<&str> {
+        match self {
+            Self::Processing { error, .. } => error.node_path(),
+            Self::Io { .. }
+            | Self::OutOfDate { .. }
+            | Self::Directive { .. }
+            | Self::ModifiedByHand { .. }
+            | Self::Rustfmt(_)
+            | Self::Batch { .. }
+            | Self::InvalidConfigFile { .. }
+            | Self::OutputWithoutSource { .. } => None,
+        }
+    }
+
+    /// A stable, machine-readable name for the kind of error, e.g. `bspg::parse`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Io { .. } => "bspg::io",
+            Self::Processing { error, .. } => error.code(),
+            Self::OutOfDate { .. } => "bspg::out_of_date",
+            Self::Directive { .. } => "bspg::directive",
+            Self::ModifiedByHand { .. } => "bspg::modified_by_hand",
+            Self::Rustfmt(_) => "bspg::rustfmt",
+            Self::Batch { .. } => "bspg::batch",
+            Self::InvalidConfigFile { .. } => "bspg::invalid_config_file",
+            Self::OutputWithoutSource { .. } => "bspg::output_without_source",
+        }
+    }
+
+    /// An [`io::Error`] with the file at `path`
+    pub(crate) fn io(error: io::Error, path: &Path) -> Self {
+        Self::Io {
+            path: Some(path.to_path_buf()),
+            error,
+        }
+    }
+
+    /// Attribute the error to the file at `path`, unless it already names one
+    pub(crate) fn in_file(mut self, file: &Path) -> Self {
+        if let Self::Io { path, .. }
+        | Self::Processing { path, .. }
+        | Self::Directive { path, .. } = &mut self
+        {
+            path.get_or_insert_with(|| file.to_path_buf());
+        }
+        self
+    }
+
+    /// Move the spans from the states that were parsed to the file they are in
+    pub(crate) fn relocate(self, source_map: &SourceMap<'_>) -> Self {
+        match self {
+            Self::Processing { path, error } => Self::Processing {
+                path,
+                error: error.relocate(source_map),
+            },
+            error => error,
+        }
+    }
+}
+
+/// ` of path`, if there is one
+fn of_file(path: Option<&Path>) -> String {
+    path.map(|path| format!(" of {}", path.display()))
+        .unwrap_or_default()
+}
+
+/// `path:line:column: ` for as much as is known
+fn location(path: Option<&Path>, span: Option<Span>) -> String {
+    match (path, span) {
+        (Some(path), Some(span)) => format!("{}:{}:{}: ", path.display(), span.line, span.column),
+        (Some(path), None) => format!("{}: ", path.display()),
+        (None, Some(span)) => format!("line {}, column {}: ", span.line, span.column),
+        (None, None) => String::new(),
+    }
+}
+
+/// The output of a [`GeneratorError::OutputWithoutSource`], which may not have a path
+fn display_output(output: &Path) -> String {
+    if output.as_os_str().is_empty() {
+        "An output".to_string()
+    } else {
+        output.display().to_string()
+    }
+}
+
+fn display_errors(errors: &[(PathBuf, GeneratorError)]) -> String {
+    errors
+        .iter()
+        .map(|(path, error)| format!("\n{}: {error}", path.display()))
+        .collect()
 }
 
 /// ```rust no_run
 /// use bevy_state_plugin_generator::prelude::*;
 /// fn main() {
-///   update_template(
+///   let outcome = update_template(
 ///     "src/generated_states.rs",
 ///     PluginConfig::default()
 ///   ).expect("Failed to generate plugin!");
+///   if outcome.changed {
+///     println!("cargo:warning=updated {}", outcome.path.display());
+///   }
 /// }
 /// ```
 #[cfg_attr(coverage_nightly, coverage(off))]
 pub fn update_template(
     template_path: impl AsRef<Path>,
-    mut plugin_config: PluginConfig,
-) -> Result<(), GeneratorError> {
-    let src_display = template_path.as_ref().to_string_lossy();
-    println!("cargo:rerun-if-changed={src_display}");
-    let source = std::fs::read_to_string(&template_path)?;
-    let header = parse_template_header(&source, &mut plugin_config);
-
-    let plugin_source =
-        generate_state_plugin_source(&header.template.join("\n"), plugin_config, None)?;
-
-    let header = concat([
-        header
-            .info_block
-            .into_iter()
-            .map(|line| format!("// {line}"))
-            .collect_vec(),
-        // vec![
-        //     format!("comment_block={comment_block:?}"),
-        //     format!("info_and_warnings={info_and_warnings:?}"),
-        // ],
-        header
-            .comments_block
-            .into_iter()
-            .map(String::from)
-            .collect_vec(),
-    ])
-    .join("\n");
-
-    fs::write(&template_path, format!("{header}\n\n{plugin_source}")).map_err(Into::into)
+    plugin_config: PluginConfig,
+) -> Result<GenerationOutcome, GeneratorError> {
+    Generator::new()
+        .template(template_path, plugin_config)
+        .run_single()
 }
 
+/// The hash in the header of the generated file protects it against being
+/// overwritten after it was edited by hand, unless [`PluginConfig::force`] is set.
 /// ```rust no_run
 /// use bevy_state_plugin_generator::prelude::*;
 /// fn main() {
@@ -81,10 +305,437 @@ pub fn generate_plugin(
     src: impl AsRef<Path>,
     dst: impl AsRef<Path>,
     plugin_config: PluginConfig,
+) -> Result<GenerationOutcome, GeneratorError> {
+    Generator::new()
+        .source(src)
+        .output(dst, plugin_config)
+        .run_single()
+}
+
+/// Generate the plugin from the states in `src`, configured by the directives at
+/// the top of the file. It is written to the path of the `// bspg:output`
+/// directive, relative to `src`, or next to `src` with the `.rs` extension.
+/// ```text
+/// // bspg:naming_scheme short
+/// // bspg:plugin_name UiStatesPlugin
+/// // bspg:output "../generated/ui_states.rs"
+/// Hud Menu { Main Options }
+/// ```
+/// ```rust no_run
+/// use bevy_state_plugin_generator::prelude::*;
+/// fn main() {
+///   for entry in std::fs::read_dir("src/states").unwrap() {
+///     let path = entry.unwrap().path();
+///     if path.extension().is_some_and(|extension| extension == "txt") {
+///       generate_plugin_from_source(path, PluginConfig::default())
+///         .expect("Failed to generate plugin!");
+///     }
+///   }
+/// }
+/// ```
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn generate_plugin_from_source(
+    src: impl AsRef<Path>,
+    plugin_config: PluginConfig,
+) -> Result<GenerationOutcome, GeneratorError> {
+    Generator::new()
+        .source(src)
+        .output_from_source(plugin_config)
+        .run_single()
+}
+
+/// Check that a file written by [`update_template`] is up-to-date, without
+/// rewriting it. Fails with [`GeneratorError::OutOfDate`] otherwise.
+/// ```rust no_run
+/// use bevy_state_plugin_generator::prelude::*;
+/// #[test]
+/// fn generated_states_are_up_to_date() {
+///   check_template("src/generated_states.rs", PluginConfig::default())
+///     .expect("Generated states are stale!");
+/// }
+/// ```
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn check_template(
+    template_path: impl AsRef<Path>,
+    plugin_config: PluginConfig,
 ) -> Result<(), GeneratorError> {
-    let src_display = src.as_ref().to_string_lossy();
-    println!("cargo:rerun-if-changed={src_display}");
-    let source = std::fs::read_to_string(&src)?;
-    let source = generate_state_plugin_source(&source, plugin_config, Some(&src_display))?;
-    fs::write(dst, source).map_err(Into::into)
+    Generator::new()
+        .check(true)
+        .template(template_path, plugin_config)
+        .run_single()
+        .map(drop)
+}
+
+/// Check that a file written by [`generate_plugin`] is up-to-date, without
+/// rewriting it. Fails with [`GeneratorError::OutOfDate`] otherwise.
+/// ```rust no_run
+/// use bevy_state_plugin_generator::prelude::*;
+/// #[test]
+/// fn generated_states_are_up_to_date() {
+///   check_plugin("src/states.txt", "src/generated_states.rs", PluginConfig::default())
+///     .expect("Generated states are stale!");
+/// }
+/// ```
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn check_plugin(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    plugin_config: PluginConfig,
+) -> Result<(), GeneratorError> {
+    Generator::new()
+        .check(true)
+        .source(src)
+        .output(dst, plugin_config)
+        .run_single()
+        .map(drop)
+}
+
+/// A generated file, before it is written
+#[derive(Clone, Debug)]
+pub(crate) struct Rendered {
+    pub source: String,
+    pub warnings: Vec<Diagnostic>,
+}
+
+impl Rendered {
+    /// Attribute the warnings to the file they were found in
+    fn found_in(mut self, path: &Path) -> Self {
+        self.warnings = self
+            .warnings
+            .into_iter()
+            .map(|warning| warning.with_path(path))
+            .collect();
+        self
+    }
+
+    /// Write the source to `path` if it changed, passing on the warnings
+    pub(crate) fn write(
+        self,
+        path: &Path,
+        force: bool,
+    ) -> Result<GenerationOutcome, GeneratorError> {
+        let outcome = write_if_changed(path, &self.source, force)?;
+        Ok(GenerationOutcome {
+            warnings: self.warnings,
+            ..outcome
+        })
+    }
+}
+
+/// The full contents of the template at `path` after updating it
+pub(crate) fn render_template_file(
+    path: &Path,
+    plugin_config: PluginConfig,
+) -> Result<Rendered, GeneratorError> {
+    let template = fs::read_to_string(path).map_err(|error| GeneratorError::io(error, path))?;
+    render_template(&template, plugin_config)
+        .map(|rendered| rendered.found_in(path))
+        .map_err(|error| error.in_file(path))
+}
+
+/// The full contents of a template after updating it
+pub(crate) fn render_template(
+    template: &str,
+    mut plugin_config: PluginConfig,
+) -> Result<Rendered, GeneratorError> {
+    let regions = find_generated_regions(template)?;
+    if has_tagged_blocks(template, &regions) {
+        let blocks = parse_template_blocks(template, &plugin_config, &regions)?;
+        return render_template_blocks(template, blocks, &regions);
+    }
+    if let Some(region) = regions.iter().find(|region| region.name != DEFAULT_REGION) {
+        return Err(unknown_region(template, region));
+    }
+    let header = parse_template_header(template, &mut plugin_config, regions.first())?;
+    let region = regions.first().map(|region| region.range.clone());
+    let mut warnings = header.warnings;
+
+    let header_style = plugin_config.header_style;
+    let source_map = SourceMap::from_lines(template, &header.template, 1);
+    let plugin = generate_state_plugin_source(&header.template.join("\n"), plugin_config, None)
+        .map_err(|error| error.relocate(&source_map))?;
+    warnings.extend(plugin.warnings);
+    let plugin_source = plugin.source;
+    let info_block = template_info(header.info_block, header_style, &plugin_source);
+
+    let source = match region {
+        // only the region is replaced, the rest is hand-written
+        Some(region) => {
+            let generated = below_header(&info_block, &plugin_source);
+            validate_output(&generated)?;
+            let (before, after) = (&template[..region.start], &template[region.end..]);
+            format!("{before}{generated}{after}")
+        }
+        None => {
+            let comments_block = header.comments_block.into_iter().map(String::from);
+            let header = concat([info_block, comments_block.collect_vec()]);
+            let source = below_header(&header, &plugin_source);
+            validate_output(&source)?;
+            source
+        }
+    };
+    Ok(Rendered { source, warnings })
+}
+
+/// The comment lines above the plugin in a template, with the hash of the
+/// plugin right below `generated by`, like in a generated file
+fn template_info(
+    info_block: Vec<String>,
+    header_style: HeaderStyle,
+    plugin_source: &str,
+) -> Vec<String> {
+    let mut lines = info_block
+        .into_iter()
+        .map(|line| format!("// {line}"))
+        .collect_vec();
+    let after = match header_style {
+        HeaderStyle::Full | HeaderStyle::Minimal => Some(1),
+        HeaderStyle::SourceHash => Some(0),
+        HeaderStyle::None => None,
+    };
+    if let Some(after) = after {
+        let hash = format!("{HASH_PREFIX}{}", content_hash(plugin_source));
+        lines.insert(after, hash);
+    }
+    lines
+}
+
+/// The plugin below the header lines, separated by an empty line
+fn below_header(header: &[String], plugin_source: &str) -> String {
+    if header.is_empty() {
+        return plugin_source.to_string();
+    }
+    format!("{}\n\n{plugin_source}", header.join("\n"))
+}
+
+fn unknown_region(template: &str, region: &GeneratedRegion) -> GeneratorError {
+    let error = DirectiveError::UnknownRegion(region.name.clone());
+    directive_error(template, region.line, error)
+}
+
+/// The import every generated plugin starts with. The blocks of a template
+/// share a module, where importing it twice would not compile.
+const STATES_IMPORT: &str = "use bevy::prelude::AppExtStates;\n";
+
+/// Replace the region of every `// bspg: <tag>` block of a template. A block
+/// without a region yet gets one right below it.
+fn render_template_blocks(
+    template: &str,
+    blocks: Vec<TemplateBlock>,
+    regions: &[GeneratedRegion],
+) -> Result<Rendered, GeneratorError> {
+    if let Some(region) = regions
+        .iter()
+        .find(|region| !blocks.iter().any(|block| block.tag == region.name))
+    {
+        return Err(unknown_region(template, region));
+    }
+    check_name_clashes(template, &blocks)?;
+
+    let mut edits = Vec::new();
+    let mut warnings = Vec::new();
+    for (index, block) in blocks.into_iter().enumerate() {
+        let header_style = block.config.header_style;
+        let source_map = SourceMap::from_lines(template, &block.template, 1);
+        let plugin = generate_state_plugin_source(&block.template.join("\n"), block.config, None)
+            .map_err(|error| error.relocate(&source_map))?;
+        warnings.extend(block.warnings);
+        warnings.extend(plugin.warnings);
+        let plugin_source = if index == 0 {
+            plugin.source
+        } else {
+            plugin.source.replacen(STATES_IMPORT, "", 1)
+        };
+        let info_block = template_info(block.info_block, header_style, &plugin_source);
+        let generated = below_header(&info_block, &plugin_source);
+        validate_output(&generated)?;
+        let edit = match regions.iter().find(|region| region.name == block.tag) {
+            Some(region) => (region.range.clone(), generated),
+            None => {
+                let newline = if template[..block.end].ends_with('\n') {
+                    ""
+                } else {
+                    "\n"
+                };
+                let tag = &block.tag;
+                let region =
+                    format!("{newline}\n// bspg:begin {tag}\n{generated}// bspg:end {tag}\n");
+                (block.end..block.end, region)
+            }
+        };
+        edits.push(edit);
+    }
+    edits.sort_by_key(|(range, _)| range.start);
+
+    let mut source = String::with_capacity(template.len());
+    let mut offset = 0;
+    for (range, generated) in edits {
+        source.push_str(&template[offset..range.start]);
+        source.push_str(&generated);
+        offset = range.end;
+    }
+    source.push_str(&template[offset..]);
+    Ok(Rendered { source, warnings })
+}
+
+/// The blocks of one template end up in the same module, so the items they
+/// generate at the top level need different names
+fn check_name_clashes(template: &str, blocks: &[TemplateBlock]) -> Result<(), GeneratorError> {
+    let mut names: Vec<(&str, &str)> = Vec::new();
+    for block in blocks {
+        let config = &block.config;
+        for name in [&*config.plugin_name, &*config.states_module_name] {
+            if let Some((_, first)) = names.iter().find(|(other, _)| *other == name) {
+                let error = DirectiveError::NameClash {
+                    name: name.to_string(),
+                    first: first.to_string(),
+                    second: block.tag.clone(),
+                };
+                return Err(directive_error(template, block.line, error));
+            }
+            names.push((name, &block.tag));
+        }
+    }
+    Ok(())
+}
+
+/// The `output` directive relative to `src`, or `src` with the `.rs` extension
+pub(crate) fn output_path(src: &Path, output: Option<&str>) -> io::Result<PathBuf> {
+    let dst = match output {
+        Some(output) => src.parent().unwrap_or(Path::new("")).join(output),
+        None => src.with_extension("rs"),
+    };
+    if dst == src {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} would overwrite its own source", dst.display()),
+        ));
+    }
+    Ok(dst)
+}
+
+/// Reformat a state definition in the canonical layout: one state per line,
+/// children indented by four spaces
+/// ```rust
+/// # use bevy_state_plugin_generator::prelude::*;
+/// let formatted = format_states("Loading, Ready{Menu Game}").unwrap();
+/// assert_eq!(formatted, "Loading\nReady {\n    Menu\n    Game\n}\n");
+/// ```
+pub fn format_states(source: &str) -> Result<String, GeneratorError> {
+    let header = parse_source_header(source, &mut PluginConfig::default())?;
+    let nodes = parse_nodes(&header.states).map_err(|error| error.relocate(&header.source_map))?;
+    let directives = header.directives.iter().map(|line| format!("{line}\n"));
+    Ok(directives.chain([print_nodes(&nodes)]).collect())
+}
+
+/// Generate the plugin into `$OUT_DIR`, keeping the source tree untouched.
+/// Include it with `include!(concat!(env!("OUT_DIR"), "/states.rs"))` or with
+/// `include_generated_states!("states.rs")` from `bevy_state_plugin_generator_macros`.
+/// ```rust no_run
+/// use bevy_state_plugin_generator::prelude::*;
+/// fn main() {
+///   generate_plugin_to_out_dir(
+///     "src/states.txt",
+///     "states.rs",
+///     PluginConfig::default()
+///   ).expect("Failed to generate plugin!");
+/// }
+/// ```
+#[cfg_attr(coverage_nightly, coverage(off))]
+pub fn generate_plugin_to_out_dir(
+    src: impl AsRef<Path>,
+    file_name: impl AsRef<Path>,
+    plugin_config: PluginConfig,
+) -> Result<GenerationOutcome, GeneratorError> {
+    Generator::new()
+        .source(src)
+        .output_to_out_dir(file_name, plugin_config)
+        .run_single()
+}
+
+/// How a source path is shown in the generated header: relative to the
+/// manifest if possible, so the output does not depend on where it was built
+pub(crate) fn display_path(path: &Path, manifest_dir: Option<OsString>) -> String {
+    manifest_dir
+        .and_then(|manifest_dir| path.strip_prefix(manifest_dir).ok())
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Make sure the output parses as Rust before it is written
+pub(crate) fn validate_output(source: &str) -> Result<(), GeneratorError> {
+    syn::parse_file(source)
+        .map(drop)
+        .map_err(|error| ProcessingError::from_syn(error).into())
+}
+
+/// Generate the plugin source from a state definition without touching the
+/// filesystem, e.g. to preview it. Directives in the source override
+/// `plugin_config`; the result has no header. Warnings are left out, use
+/// [`generate_from_str_with_diagnostics`] to get them.
+/// ```rust
+/// use bevy_state_plugin_generator::prelude::*;
+/// let source = generate_from_str("Loading Ready { Menu Game }", &PluginConfig::default())
+///     .expect("Failed to generate plugin!");
+/// assert!(source.contains("pub struct GeneratedStatesPlugin;"));
+/// ```
+pub fn generate_from_str(
+    source: &str,
+    plugin_config: &PluginConfig,
+) -> Result<String, GeneratorError> {
+    generate_from_str_with_diagnostics(source, plugin_config).map(|(source, _)| source)
+}
+
+/// [`generate_from_str`], along with the warnings about the source, like
+/// unknown directives
+/// ```rust
+/// use bevy_state_plugin_generator::prelude::*;
+/// let (_, warnings) =
+///     generate_from_str_with_diagnostics("// bspg:nonsense 1\nA", &PluginConfig::default())
+///         .expect("Failed to generate plugin!");
+/// assert_eq!(warnings[0].line, Some(1));
+/// ```
+pub fn generate_from_str_with_diagnostics(
+    source: &str,
+    plugin_config: &PluginConfig,
+) -> Result<(String, Vec<Diagnostic>), GeneratorError> {
+    let mut plugin_config = plugin_config.clone();
+    let header = parse_source_header(source, &mut plugin_config)?;
+    let rendered = parse_nodes(&header.states)
+        .and_then(|nodes| render_body(nodes, &header.states, plugin_config))
+        .map_err(|error| error.relocate(&header.source_map))?;
+    validate_output(&rendered.source)?;
+    Ok((rendered.source, header.warnings))
+}
+
+/// Generate the plugin as tokens, e.g. to be returned from a proc-macro.
+/// Warnings are left out, use [`generate_tokens_with_diagnostics`] to get them.
+/// ```rust
+/// use bevy_state_plugin_generator::prelude::*;
+/// let tokens = generate_tokens("Loading Ready { Menu Game }", PluginConfig::default())
+///     .expect("Failed to generate plugin!");
+/// assert!(tokens.to_string().contains("GeneratedStatesPlugin"));
+/// ```
+pub fn generate_tokens(
+    source: &str,
+    plugin_config: PluginConfig,
+) -> Result<TokenStream, GeneratorError> {
+    generate_tokens_with_diagnostics(source, plugin_config).map(|(tokens, _)| tokens)
+}
+
+/// [`generate_tokens`], along with the warnings about the source, like
+/// unknown directives
+pub fn generate_tokens_with_diagnostics(
+    source: &str,
+    mut plugin_config: PluginConfig,
+) -> Result<(TokenStream, Vec<Diagnostic>), GeneratorError> {
+    let header = parse_source_header(source, &mut plugin_config)?;
+    let tokens = parse_nodes(&header.states)
+        .and_then(|nodes| {
+            convert_nodes_into_plugin_tokens(nodes, plugin_config)
+                .map_err(|error| error.locate(&header.states).into())
+        })
+        .map_err(|error| error.relocate(&header.source_map))?;
+    Ok((tokens, header.warnings))
 }
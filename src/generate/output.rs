@@ -0,0 +1,115 @@
+#[cfg(test)]
+mod tests;
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use similar::TextDiff;
+
+use crate::generate::GeneratorError;
+use crate::generate::core::is_modified_by_hand;
+use crate::generate::diagnostics::Diagnostic;
+
+/// What happened to a generated file
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GenerationOutcome {
+    /// Whether the file was written; `false` if it was already up-to-date
+    pub changed: bool,
+    /// The generated file
+    pub path: PathBuf,
+    /// Problems that did not stop the generation
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// Write `source` to `path`, unless the file already contains exactly that.
+/// Leaving an up-to-date file alone keeps its mtime, which avoids needless
+/// rebuilds and re-indexing. A file that was modified by hand since it was
+/// generated is only overwritten with `force`.
+pub(crate) fn write_if_changed(
+    path: impl AsRef<Path>,
+    source: &str,
+    force: bool,
+) -> Result<GenerationOutcome, GeneratorError> {
+    let path = path.as_ref();
+    let changed = match fs::read(path) {
+        Ok(existing) if existing == source.as_bytes() => false,
+        Ok(existing) if !force && str::from_utf8(&existing).is_ok_and(is_modified_by_hand) => {
+            return Err(GeneratorError::ModifiedByHand {
+                path: path.to_path_buf(),
+            });
+        }
+        Ok(_) => true,
+        Err(error) if error.kind() == ErrorKind::NotFound => true,
+        Err(error) => return Err(GeneratorError::io(error, path)),
+    };
+    if changed {
+        write_atomically(path, source).map_err(|error| GeneratorError::io(error, path))?;
+    }
+    Ok(GenerationOutcome {
+        changed,
+        path: path.to_path_buf(),
+        warnings: Vec::new(),
+    })
+}
+
+/// Write to a temporary file next to `path` and rename it into place, so an
+/// interrupted build never leaves a truncated file behind. The permissions of
+/// an existing file are kept.
+fn write_atomically(path: &Path, source: &str) -> io::Result<()> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("{} is not a file path", path.display()),
+        )
+    })?;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(
+        ".{}-{}.tmp",
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = (|| -> io::Result<()> {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(source.as_bytes())?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp_path, metadata.permissions())?;
+        }
+        fs::rename(&temp_path, path)
+    })();
+    if result.is_err() {
+        // the temporary file may or may not exist at this point
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Compare the file at `path` with its `expected` contents, without writing.
+/// A missing file is compared as if it were empty.
+pub(crate) fn check_up_to_date(path: &Path, expected: &str) -> Result<(), GeneratorError> {
+    let existing = match fs::read_to_string(path) {
+        Ok(existing) => existing,
+        Err(error) if error.kind() == ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(GeneratorError::io(error, path)),
+    };
+    if existing == expected {
+        return Ok(());
+    }
+    let path_display = path.display().to_string();
+    let diff = TextDiff::from_lines(existing.as_str(), expected)
+        .unified_diff()
+        .header(&path_display, &format!("{path_display} (generated)"))
+        .to_string();
+    Err(GeneratorError::OutOfDate {
+        path: path.to_path_buf(),
+        diff,
+    })
+}
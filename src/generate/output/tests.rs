@@ -0,0 +1,137 @@
+use std::fs;
+
+use rstest::rstest;
+use speculoos::prelude::*;
+use tempfile::TempDir;
+
+use crate::generate::GeneratorError;
+use crate::generate::core::generate_state_plugin_source;
+use crate::generate::output::{check_up_to_date, write_if_changed};
+
+#[rstest]
+fn test_write_if_changed_creates_missing_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("states.rs");
+    let outcome = write_if_changed(&path, "pub struct A;\n", false).unwrap();
+    assert_that!(outcome.changed).is_true();
+    assert_that!(outcome.path).is_equal_to(path.clone());
+    assert_that!(fs::read_to_string(&path).unwrap()).is_equal_to("pub struct A;\n".to_string());
+}
+
+#[rstest]
+#[case::same("pub struct A;\n", false)]
+#[case::different("pub struct B;\n", true)]
+fn test_write_if_changed_compares_contents(#[case] source: &str, #[case] changed: bool) {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("states.rs");
+    fs::write(&path, "pub struct A;\n").unwrap();
+    let modified = fs::metadata(&path).unwrap().modified().unwrap();
+
+    let outcome = write_if_changed(&path, source, false).unwrap();
+    assert_that!(outcome.changed).is_equal_to(changed);
+    assert_that!(fs::read_to_string(&path).unwrap()).is_equal_to(source.to_string());
+    if !changed {
+        assert_that!(fs::metadata(&path).unwrap().modified().unwrap()).is_equal_to(modified);
+    }
+}
+
+#[rstest]
+fn test_write_if_changed_reports_io_errors() {
+    let dir = TempDir::new().unwrap();
+    // a directory cannot be read as a file
+    assert_that!(write_if_changed(dir.path(), "", false)).is_err();
+}
+
+#[rstest]
+fn test_check_up_to_date_accepts_matching_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("states.rs");
+    fs::write(&path, "pub struct A;\n").unwrap();
+    assert_that!(check_up_to_date(&path, "pub struct A;\n")).is_ok();
+}
+
+#[rstest]
+#[case::stale(Some("pub struct A;\npub struct B;\n"))]
+#[case::missing(None)]
+fn test_check_up_to_date_reports_diff(#[case] existing: Option<&str>) {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("states.rs");
+    if let Some(existing) = existing {
+        fs::write(&path, existing).unwrap();
+    }
+    let error = check_up_to_date(&path, "pub struct A;\npub struct C;\n").unwrap_err();
+    let GeneratorError::OutOfDate {
+        path: stale_path,
+        diff,
+    } = error
+    else {
+        panic!("expected OutOfDate, got {error:?}");
+    };
+    assert_that!(stale_path).is_equal_to(path);
+    assert_that!(diff.as_str()).contains("+pub struct C;\n");
+    if existing.is_some() {
+        assert_that!(diff.as_str()).contains("-pub struct B;\n");
+    }
+    // the file is left untouched
+    assert_that!(fs::read_to_string(dir.path().join("states.rs")).ok())
+        .is_equal_to(existing.map(String::from));
+}
+
+#[rstest]
+#[case::unmodified(false, false)]
+#[case::modified(true, false)]
+#[case::modified_forced(true, true)]
+fn test_write_if_changed_protects_hand_edits(#[case] modified: bool, #[case] force: bool) {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("states.rs");
+    let old = generate_state_plugin_source("Loading", Default::default(), Some("states.txt"));
+    let mut old = old.unwrap().source;
+    if modified {
+        old = old.replace("Loading", "Booting");
+    }
+    fs::write(&path, &old).unwrap();
+    let new = generate_state_plugin_source("Ready", Default::default(), Some("states.txt"));
+    let new = new.unwrap().source;
+
+    let result = write_if_changed(&path, &new, force);
+    if modified && !force {
+        let Err(GeneratorError::ModifiedByHand {
+            path: modified_path,
+        }) = result
+        else {
+            panic!("expected ModifiedByHand, got {result:?}");
+        };
+        assert_that!(modified_path).is_equal_to(path.clone());
+        assert_that!(fs::read_to_string(&path).unwrap()).is_equal_to(old);
+    } else {
+        assert_that!(result.unwrap().changed).is_true();
+        assert_that!(fs::read_to_string(&path).unwrap()).is_equal_to(new);
+    }
+}
+
+#[rstest]
+fn test_write_if_changed_leaves_no_temporary_files() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("states.rs");
+    write_if_changed(&path, "pub struct A;\n", false).unwrap();
+    write_if_changed(&path, "pub struct B;\n", false).unwrap();
+    let files = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect::<Vec<_>>();
+    assert_that!(files).is_equal_to(vec!["states.rs".into()]);
+}
+
+#[cfg(unix)]
+#[rstest]
+fn test_write_if_changed_keeps_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("states.rs");
+    fs::write(&path, "pub struct A;\n").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+    write_if_changed(&path, "pub struct B;\n", false).unwrap();
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    assert_that!(mode & 0o777).is_equal_to(0o640);
+}
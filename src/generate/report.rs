@@ -0,0 +1,214 @@
+#[cfg(test)]
+mod tests;
+
+use std::{fmt, fs};
+
+use miette::{GraphicalReportHandler, GraphicalTheme, LabeledSpan, NamedSource, SourceSpan};
+
+use crate::generate::GeneratorError;
+use crate::parsing::span::Span;
+use crate::processing::ProcessingError;
+
+/// A [`GeneratorError`] rendered as annotated snippets of the source it is
+/// about, e.g. both states that get the same name. Its `Debug` output is the
+/// rendered report, so a build script that returns it or `expect`s it shows
+/// the snippets when it fails.
+pub struct ErrorReport {
+    message: String,
+    code: &'static str,
+    help: Option<String>,
+    source_code: Option<NamedSource<String>>,
+    labels: Vec<LabeledSpan>,
+    related: Vec<ErrorReport>,
+}
+
+impl GeneratorError {
+    /// The error with snippets of the file it is about, which is read again
+    /// from [`path`](Self::path)
+    pub fn report(self) -> ErrorReport {
+        let source = self
+            .path()
+            .filter(|_| points_into_source(&self))
+            .and_then(|path| fs::read_to_string(path).ok());
+        ErrorReport::new(self, source)
+    }
+
+    /// The error with snippets of `source`, for a source that was not read
+    /// from a file, e.g. by [`generate_from_str`](crate::prelude::generate_from_str)
+    pub fn report_with_source(self, source: impl Into<String>) -> ErrorReport {
+        ErrorReport::new(self, Some(source.into()))
+    }
+}
+
+impl From<GeneratorError> for ErrorReport {
+    fn from(error: GeneratorError) -> Self {
+        error.report()
+    }
+}
+
+impl ErrorReport {
+    fn new(error: GeneratorError, source: Option<String>) -> Self {
+        let labels = source
+            .as_deref()
+            .map(|source| labels(&error, source))
+            .unwrap_or_default();
+        let name = error
+            .path()
+            .map_or_else(|| "<source>".to_string(), |path| path.display().to_string());
+        let code = error.code();
+        let help = help(&error);
+        let (message, related) = match error {
+            GeneratorError::Batch { errors } => (
+                format!("{} of the generated files failed", errors.len()),
+                errors
+                    .into_iter()
+                    .map(|(_, error)| error.report())
+                    .collect(),
+            ),
+            // the snippet shows where it is
+            GeneratorError::Processing { error, .. } => (error.to_string(), Vec::new()),
+            error => (error.to_string(), Vec::new()),
+        };
+        Self {
+            message,
+            code,
+            help,
+            source_code: source.map(|source| NamedSource::new(name, source)),
+            labels,
+            related,
+        }
+    }
+
+    /// The report as text, in color if the terminal supports it
+    pub fn render(&self) -> String {
+        self.render_with(GraphicalTheme::default())
+    }
+
+    pub(crate) fn render_with(&self, theme: GraphicalTheme) -> String {
+        let mut rendered = String::new();
+        GraphicalReportHandler::new_themed(theme)
+            .render_report(&mut rendered, self)
+            .expect("Failed to render error report!");
+        rendered
+    }
+}
+
+/// Whether there is anything to point at in the source
+fn points_into_source(error: &GeneratorError) -> bool {
+    error.span().is_some()
+}
+
+/// What to point at in the source
+fn labels(error: &GeneratorError, source: &str) -> Vec<LabeledSpan> {
+    let at = |span: Span, label: String| LabeledSpan::at(SourceSpan::from(span.range()), label);
+    match error {
+        GeneratorError::Processing {
+            error: ProcessingError::DuplicateName(duplicate),
+            ..
+        } => {
+            let name = &duplicate.resolved_name;
+            duplicate
+                .first_span
+                .map(|span| at(span, format!("`{name}` is first used here")))
+                .into_iter()
+                .chain(duplicate.span.map(|span| {
+                    LabeledSpan::new_primary_with_span(
+                        Some(format!("and again here, by {}", duplicate.node_path)),
+                        span.range(),
+                    )
+                }))
+                .collect()
+        }
+        GeneratorError::Processing {
+            error: ProcessingError::Parse(failure),
+            ..
+        } => match (failure.unclosed, failure.span) {
+            (Some(unclosed), Some(span)) => {
+                let close = closing_delimiter(source, unclosed);
+                vec![
+                    at(unclosed, "this is never closed".to_string()),
+                    at(span, format!("expected `{close}` here")),
+                ]
+            }
+            (None, Some(span)) => vec![at(span, "parsing stopped here".to_string())],
+            (_, None) => Vec::new(),
+        },
+        GeneratorError::Processing {
+            error: ProcessingError::InvalidName {
+                span: Some(span), ..
+            },
+            ..
+        } => vec![at(*span, "not a valid Rust identifier".to_string())],
+        GeneratorError::Directive { span, error, .. } => vec![at(*span, error.to_string())],
+        _ => Vec::new(),
+    }
+}
+
+/// The `}` or `]` that would close the delimiter at `unclosed`
+fn closing_delimiter(source: &str, unclosed: Span) -> char {
+    match source.get(unclosed.range()) {
+        Some("[") => ']',
+        _ => '}',
+    }
+}
+
+fn help(error: &GeneratorError) -> Option<String> {
+    match error {
+        GeneratorError::Processing {
+            error: ProcessingError::DuplicateName(_),
+            ..
+        } => Some("rename one of them, or use a naming scheme that tells them apart".to_string()),
+        GeneratorError::Processing {
+            error: ProcessingError::Parse(failure),
+            ..
+        } if failure.unclosed.is_some() => Some("close it where the enum or list ends".to_string()),
+        _ => None,
+    }
+}
+
+impl fmt::Display for ErrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl fmt::Debug for ErrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render())
+    }
+}
+
+impl std::error::Error for ErrorReport {}
+
+impl miette::Diagnostic for ErrorReport {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(self.code))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.help
+            .as_ref()
+            .map(|help| Box::new(help) as Box<dyn fmt::Display>)
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        self.source_code
+            .as_ref()
+            .map(|source| source as &dyn miette::SourceCode)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        (self.source_code.is_some() && !self.labels.is_empty())
+            .then(|| Box::new(self.labels.iter().cloned()) as Box<dyn Iterator<Item = _>>)
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn miette::Diagnostic> + 'a>> {
+        (!self.related.is_empty()).then(|| {
+            Box::new(
+                self.related
+                    .iter()
+                    .map(|report| report as &dyn miette::Diagnostic),
+            ) as Box<dyn Iterator<Item = _>>
+        })
+    }
+}
@@ -0,0 +1,70 @@
+use miette::GraphicalTheme;
+
+use crate::generate::{GeneratorError, check_plugin, generate_from_str, render_template};
+use crate::prelude::{NamingScheme, PluginConfig};
+use crate::set_snapshot_suffix;
+use crate::testing::*;
+
+fn config() -> PluginConfig {
+    PluginConfig::default().with_naming_scheme(NamingScheme::None)
+}
+
+fn generate_template(template: &str, config: &PluginConfig) -> Result<String, GeneratorError> {
+    render_template(template, config.clone()).map(|rendered| rendered.source)
+}
+
+fn render(error: GeneratorError, source: &str) -> String {
+    error
+        .report_with_source(source)
+        .render_with(GraphicalTheme::unicode_nocolor())
+}
+
+#[rstest]
+#[case::duplicate_source(
+    "// bspg:plugin_name MenuPlugin\nMenu\nReady { Menu }\n",
+    generate_from_str
+)]
+#[case::duplicate_template(
+    "// bspg:\n// Menu\n// Ready { Menu }\n\nfn main() {}\n",
+    generate_template
+)]
+#[case::unclosed_source(
+    "// bspg:plugin_name MenuPlugin\nLoading\nReady { Menu Game\n",
+    generate_from_str
+)]
+#[case::unclosed_template(
+    "// bspg:\n// Loading\n// Ready { Menu [ Game\n\nfn main() {}\n",
+    generate_template
+)]
+#[case::unparsed_source("Loading\nReady ; Menu\n", generate_from_str)]
+#[case::directive_template("// bspg:nonsense\n// bspg:\n// Loading\n", generate_template)]
+fn test_report(
+    #[context] context: Context,
+    #[case] source: &str,
+    #[case] generate: fn(&str, &PluginConfig) -> Result<String, GeneratorError>,
+) {
+    let error = generate(source, &config().with_strict(true)).unwrap_err();
+    set_snapshot_suffix!("{}", context.description.unwrap());
+    assert_snapshot!(render(error, source));
+}
+
+#[rstest]
+fn test_report_reads_the_file() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let src = dir.path().join("states.txt");
+    std::fs::write(&src, "Menu\nReady { Menu }\n").unwrap();
+    let rendered = check_plugin(&src, dir.path().join("states.rs"), config())
+        .unwrap_err()
+        .report()
+        .render_with(GraphicalTheme::unicode_nocolor());
+    assert_that!(rendered).contains(src.display().to_string().as_str());
+    assert_that!(rendered).contains("2 │ Ready { Menu }");
+}
+
+#[rstest]
+fn test_report_without_a_source() {
+    let error = GeneratorError::from(std::io::Error::other("disk on fire"));
+    let report = error.report();
+    assert_that!(report.to_string()).is_equal_to("Io Error: disk on fire".to_string());
+    assert_that!(report.render_with(GraphicalTheme::unicode_nocolor())).contains("bspg::io");
+}
@@ -1,18 +1,30 @@
 use std::borrow::Cow;
+use std::ffi::OsString;
+use std::path::Path;
 use std::time::Duration;
 
 use bevy_utils::default;
+use indoc::formatdoc;
 use insta::{assert_debug_snapshot, assert_snapshot};
 use itertools::Itertools;
 use rstest::{Context, rstest};
 use speculoos::assert_that;
-use speculoos::prelude::VecAssertions;
+use speculoos::prelude::{StrAssertions, VecAssertions};
 
-use crate::generate::core::{format_source, generate_debug_info};
-use crate::generate::{GeneratorError, generate_state_plugin_source};
+use crate::generate::core::{content_hash, format_source, format_tokens, generate_debug_info,
+                            is_modified_by_hand, parse_states};
+use crate::generate::diagnostics::Diagnostic;
+use crate::generate::{GeneratorError, check_plugin, display_path, format_states,
+                      generate_from_str, generate_from_str_with_diagnostics,
+                      generate_state_plugin_source, generate_tokens,
+                      generate_tokens_with_diagnostics, output_path, render_template,
+                      render_template_file, validate_output};
 use crate::parsing::Node;
-use crate::prelude::{NamingScheme, PluginConfig};
-use crate::processing::{convert_nodes_into_plugin_source, process_nodes};
+use crate::parsing::directive::DirectiveError;
+use crate::prelude::{HeaderStyle, NamingScheme, PluginConfig, StateNode};
+#[cfg(feature = "rustfmt")]
+use crate::prelude::{RustfmtConfig, RustfmtFailure};
+use crate::processing::{ProcessingError, convert_nodes_into_plugin_source, process_nodes};
 use crate::set_snapshot_suffix;
 use crate::testing::node;
 
@@ -26,10 +38,71 @@ const RUSTFMT: &str = "_no_rustfmt";
 #[async_std::test]
 async fn test_format_source() {
     set_snapshot_suffix!("formatted{RUSTFMT}");
-    let formatted = format_source("fn main(){println!(\"Hello, world!\");}");
+    let formatted = format_source("fn main(){println!(\"Hello, world!\");}", &default());
+    let formatted = formatted.unwrap().source;
     assert_snapshot!(formatted);
 }
 
+#[cfg(feature = "rustfmt")]
+#[rstest]
+#[case::ignore(RustfmtFailure::Ignore, 0)]
+#[case::warn(RustfmtFailure::Warn, 1)]
+fn test_format_source_keeps_unformatted_source(
+    #[case] on_failure: RustfmtFailure,
+    #[case] warnings: usize,
+) {
+    let rustfmt = RustfmtConfig::default()
+        .with_edition("1999")
+        .with_on_failure(on_failure);
+    let formatted = format_source("fn main(){}", &rustfmt).unwrap();
+    assert_that!(formatted.source).is_equal_to("fn main(){}\n".to_string());
+    assert_that!(formatted.warnings).has_length(warnings);
+    if let Some(warning) = formatted.warnings.first() {
+        assert_that!(warning.to_string().as_str()).starts_with("rustfmt failed: ");
+        assert_that!(warning.to_string().as_str()).contains("--edition");
+    }
+}
+
+#[cfg(feature = "rustfmt")]
+#[rstest]
+fn test_format_source_fails() {
+    let rustfmt = RustfmtConfig::default()
+        .with_edition("1999")
+        .with_on_failure(RustfmtFailure::Error);
+    let result = format_source("fn main(){}", &rustfmt);
+    let Err(GeneratorError::Rustfmt(message)) = result else {
+        panic!("expected a rustfmt error, got {result:?}");
+    };
+    assert_that!(message.as_str()).contains("--edition");
+}
+
+#[cfg(feature = "rustfmt")]
+#[rstest]
+fn test_format_source_with_options() {
+    let rustfmt = RustfmtConfig::default().with_option("hard_tabs", true);
+    let formatted = format_source("fn main(){let a=1;}", &rustfmt).unwrap();
+    assert_that!(formatted.source).is_equal_to("fn main() {\n\tlet a = 1;\n}\n".to_string());
+}
+
+#[cfg(feature = "rustfmt")]
+#[rstest]
+fn test_format_source_with_config_path() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let config_path = dir.path().join("rustfmt.toml");
+    std::fs::write(&config_path, "hard_tabs = true\n").unwrap();
+    let rustfmt = RustfmtConfig::default().with_config_path(config_path);
+    let formatted = format_source("fn main(){let a=1;}", &rustfmt).unwrap();
+    assert_that!(formatted.source).is_equal_to("fn main() {\n\tlet a = 1;\n}\n".to_string());
+}
+
+#[cfg(feature = "rustfmt")]
+#[rstest]
+fn test_format_source_disabled() {
+    let rustfmt = RustfmtConfig::disabled().with_on_failure(RustfmtFailure::Error);
+    let formatted = format_source("fn main(){let a=1;}", &rustfmt).unwrap();
+    assert_that!(formatted.source).is_equal_to("fn main(){let a=1;}\n".to_string());
+}
+
 #[rstest]
 fn test_generate_states_plugin() {
     let root_state = Node::enumeration(
@@ -65,7 +138,8 @@ fn test_generate_states_plugin() {
 #[case("fruits.txt", "Apple Orange { O1 O2 }")]
 fn test_generate_debug_info(#[case] src_path: &str, #[case] source: &str) {
     set_snapshot_suffix!("{src_path}{RUSTFMT}");
-    assert_snapshot!(generate_debug_info(src_path, source));
+    let header = generate_debug_info(HeaderStyle::Full, src_path, source, "pub struct A;\n");
+    assert_snapshot!(header);
 }
 
 #[rstest]
@@ -87,7 +161,11 @@ fn test_generate_full_source(
         .clone()
         .unwrap_or(Cow::Borrowed("None"));
     set_snapshot_suffix!("{src_path}_{root_state_name}_{RUSTFMT}");
-    assert_snapshot!(generate_state_plugin_source(source, config, Some(src_path)).unwrap());
+    assert_snapshot!(
+        generate_state_plugin_source(source, config, Some(src_path))
+            .unwrap()
+            .source
+    );
 }
 
 #[rstest]
@@ -103,7 +181,8 @@ fn test_generate_full_source_no_implicit_root(
     #[case] plugin_config: PluginConfig,
 ) {
     set_snapshot_suffix!("{src_path}{RUSTFMT}");
-    assert_snapshot!(generate_state_plugin_source(source, plugin_config, Some(src_path)).unwrap());
+    let rendered = generate_state_plugin_source(source, plugin_config, Some(src_path)).unwrap();
+    assert_snapshot!(rendered.source);
 }
 
 #[rstest]
@@ -124,7 +203,8 @@ fn test_naming_scheme(
         },
         src_path,
     )
-    .unwrap();
+    .unwrap()
+    .source;
 
     assert_that!(result.matches(" mod ").collect_vec()).has_length(1);
     assert_snapshot!(result);
@@ -186,3 +266,491 @@ fn snapshots(
         None
     ));
 }
+
+#[rstest]
+fn test_generate_tokens_is_formatted_in_process() {
+    let tokens = generate_tokens("// the first state\nA B", default()).unwrap();
+    let source = format_tokens(tokens).unwrap();
+    assert_that!(source.as_str()).contains("/// the first state\n");
+    assert_that!(source.as_str()).contains("pub struct GameStateA;");
+}
+
+#[rstest]
+#[case::trailing_input("A ; B", "; B")]
+#[case::unclosed_enum("A { B", "line 1, column 6: Failed to parse the end of the input")]
+#[case::invalid_derive("A", "Not A Path")]
+fn test_generate_tokens_errors(#[case] source: &str, #[case] expected: &str) {
+    let config = PluginConfig::default().with_additional_derives(["Not A Path"]);
+    let error = generate_tokens(source, config).unwrap_err();
+    assert_that!(error.to_string()).contains(expected);
+}
+
+#[rstest]
+#[case::generated(&generate_state_plugin_source("A B", default(), Some("ab.txt")).unwrap().source,
+                  true)]
+#[case::unbalanced("pub mod states {", false)]
+#[case::invalid_item("pub struct 1A;", false)]
+fn test_validate_output(#[case] source: &str, #[case] valid: bool) {
+    assert_that!(validate_output(source).is_ok()).is_equal_to(valid);
+}
+
+#[rstest]
+#[case::relative("src/states.txt", Some("/crate"), "src/states.txt")]
+#[case::inside_manifest("/crate/src/states.txt", Some("/crate"), "src/states.txt")]
+#[case::outside_manifest("/other/states.txt", Some("/crate"), "/other/states.txt")]
+#[case::no_manifest("/crate/src/states.txt", None, "/crate/src/states.txt")]
+fn test_display_path(
+    #[case] path: &str,
+    #[case] manifest_dir: Option<&str>,
+    #[case] expected: &str,
+) {
+    let manifest_dir = manifest_dir.map(OsString::from);
+    assert_that!(display_path(Path::new(path), manifest_dir)).is_equal_to(expected.to_string());
+}
+
+#[rstest]
+fn test_render_template_is_stable() {
+    let template = indoc::indoc! {"
+        // bspg:naming_scheme short
+        // bspg:
+        // Loading
+        // Ready { Menu Game }
+    "};
+    let rendered = render_template(template, default()).unwrap().source;
+    assert_that!(rendered.as_str()).contains("pub enum GameStateReady");
+    // checking a freshly generated file never reports it as stale
+    assert_that!(render_template(&rendered, default()).unwrap().source).is_equal_to(rendered);
+}
+
+#[rstest]
+#[case::default("src/states/ui.txt", None, Some("src/states/ui.rs"))]
+#[case::relative(
+    "src/states/ui.txt",
+    Some("ui_states.rs"),
+    Some("src/states/ui_states.rs")
+)]
+#[case::parent("src/states/ui.txt", Some("../ui.rs"), Some("src/states/../ui.rs"))]
+#[case::same_file("src/ui.rs", None, None)]
+fn test_output_path(
+    #[case] src: &str,
+    #[case] output: Option<&str>,
+    #[case] expected: Option<&str>,
+) {
+    let dst = output_path(Path::new(src), output).ok();
+    assert_that!(dst).is_equal_to(expected.map(Into::into));
+}
+
+#[rstest]
+fn test_generate_tokens_applies_source_directives() {
+    let source = "// bspg:plugin_name ui_plugin\n// bspg:naming_scheme short\nHud { Map }";
+    let tokens = generate_tokens(source, default()).unwrap().to_string();
+    assert_that!(tokens.as_str()).contains("pub fn ui_plugin");
+    assert_that!(tokens.as_str()).contains("HudMap");
+    assert_that!(tokens.as_str()).does_not_contain("bspg");
+}
+
+#[rstest]
+fn test_format_states_keeps_directives() {
+    let formatted = format_states("// bspg:output \"ui.rs\"\nHud,Menu{Main}").unwrap();
+    assert_snapshot!(formatted, @r#"
+    // bspg:output "ui.rs"
+    Hud
+    Menu {
+        Main
+    }
+    "#);
+}
+
+#[rstest]
+fn test_render_template_file_returns_warnings() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("states.rs");
+    std::fs::write(&path, "// bspg:nonsense 1\n// bspg:\n// Loading\n").unwrap();
+    let rendered = render_template_file(&path, default()).unwrap();
+    assert_that!(rendered.warnings).has_length(1);
+    let warning = &rendered.warnings[0];
+    assert_that!(warning.path.as_deref()).is_equal_to(Some(path.as_path()));
+    assert_that!(warning.line).is_equal_to(Some(1));
+    assert_that!(rendered.source.as_str()).contains("// WARN: line 1: unknown setting: 'nonsense'");
+
+    let outcome = rendered.write(&path, false).unwrap();
+    assert_that!(outcome.warnings).has_length(1);
+}
+
+#[rstest]
+fn test_render_template_replaces_only_the_generated_region() {
+    let before = indoc::indoc! {"
+        // bspg:naming_scheme short
+        // bspg:
+        // Loading
+        // Ready { Menu Game }
+
+        use bevy::prelude::*;
+
+        // bspg:begin generated
+    "};
+    let after = indoc::indoc! {"
+        // bspg:end generated
+
+        impl states::GameStateReady {
+            fn is_menu(&self) -> bool {
+                matches!(self, Self::Menu)
+            }
+        }"};
+    let template = format!("{before}stale contents\n{after}");
+    let rendered = render_template(&template, default()).unwrap().source;
+    assert_that!(rendered.as_str()).starts_with(before);
+    assert_that!(rendered.as_str()).ends_with(after);
+    assert_that!(rendered.as_str()).does_not_contain("stale contents");
+    assert_snapshot!(&rendered[before.len()..rendered.len() - after.len()]);
+    assert_that!(render_template(&rendered, default()).unwrap().source).is_equal_to(rendered);
+}
+
+const TAGGED_BLOCKS: &str = indoc::indoc! {"
+    // bspg:plugin_name UiStatesPlugin
+    // bspg:states_module_name ui_states
+    // bspg: ui
+    // Hud
+    // Menu { Main Options }
+
+    // bspg:plugin_name GameplayStatesPlugin
+    // bspg:states_module_name gameplay_states
+    // bspg:root_state_name false
+    // bspg: gameplay
+    // Playing
+    // Paused
+
+    fn main() {}
+"};
+
+#[rstest]
+fn test_render_template_with_tagged_blocks() {
+    let rendered = render_template(TAGGED_BLOCKS, default()).unwrap().source;
+    validate_output(&rendered).unwrap();
+    assert_that!(rendered.as_str()).ends_with("// bspg:end gameplay\n\nfn main() {}\n");
+    assert_snapshot!(rendered);
+    assert_that!(render_template(&rendered, default()).unwrap().source).is_equal_to(rendered);
+}
+
+#[rstest]
+fn test_render_template_with_tagged_blocks_keeps_the_rest() {
+    let rendered = render_template(TAGGED_BLOCKS, default()).unwrap().source;
+    let edited = rendered
+        .replace("// Paused", "// Paused\n// Won")
+        .replace("fn main() {}", "fn main() {\n    // hand-written\n}");
+    let rendered = render_template(&edited, default()).unwrap().source;
+    assert_that!(rendered.as_str()).contains("pub struct Won;");
+    assert_that!(rendered.as_str()).ends_with("fn main() {\n    // hand-written\n}\n");
+}
+
+#[rstest]
+#[case::default_names(
+    "// bspg: ui\n// A\n\n// bspg: gameplay\n// B\n",
+    "Directive Error on line 4: 'GeneratedStatesPlugin' is generated by both the 'ui' and the \
+     'gameplay' block"
+)]
+#[case::same_module(
+    indoc::indoc! {"
+        // bspg:plugin_name UiPlugin
+        // bspg: ui
+        // A
+
+        // bspg:plugin_name GameplayPlugin
+        // bspg: gameplay
+        // B
+    "},
+    "Directive Error on line 6: 'states' is generated by both the 'ui' and the 'gameplay' block"
+)]
+#[case::unknown_region(
+    "// bspg: ui\n// A\n// bspg:begin gameplay\n// bspg:end gameplay\n",
+    "Directive Error on line 3: there is no `// bspg: gameplay` block for the \
+     `// bspg:begin gameplay` region"
+)]
+#[case::unknown_region_untagged(
+    "// bspg:\n// A\n// bspg:begin ui\n// bspg:end ui\n",
+    "Directive Error on line 3: there is no `// bspg: ui` block for the `// bspg:begin ui` region"
+)]
+fn test_render_template_with_tagged_blocks_errors(#[case] template: &str, #[case] expected: &str) {
+    let error = render_template(template, default()).unwrap_err();
+    assert_that!(error.to_string()).is_equal_to(expected.to_string());
+}
+
+#[rstest]
+#[case::empty("", "cbf29ce484222325")]
+#[case::text("pub struct A;\n", "e8f90629e07cc0b3")]
+fn test_content_hash(#[case] body: &str, #[case] expected: &str) {
+    assert_that!(content_hash(body)).is_equal_to(expected.to_string());
+}
+
+#[rstest]
+#[case::generated(None, false)]
+#[case::edited(Some(("Ready,", "Waiting,")), true)]
+#[case::edited_header(Some(("// src: states.txt", "// src: other.txt")), false)]
+#[case::no_hash(Some(("// hash: ", "// ")), false)]
+fn test_is_modified_by_hand(#[case] edit: Option<(&str, &str)>, #[case] expected: bool) {
+    let mut contents = generate_state_plugin_source("Loading Ready", default(), Some("states.txt"))
+        .unwrap()
+        .source;
+    if let Some((from, to)) = edit {
+        contents = contents.replacen(from, to, 1);
+    }
+    assert_that!(is_modified_by_hand(&contents)).is_equal_to(expected);
+}
+
+#[rstest]
+#[case::minimal(HeaderStyle::Minimal)]
+#[case::source_hash(HeaderStyle::SourceHash)]
+#[case::none(HeaderStyle::None)]
+fn test_generate_debug_info_header_style(#[case] header_style: HeaderStyle) {
+    set_snapshot_suffix!("{}", header_style.tag());
+    let header = generate_debug_info(header_style, "root.txt", "RootState", "pub struct A;\n");
+    assert_snapshot!(header);
+}
+
+#[rstest]
+fn test_generate_plugin_source_without_header() {
+    let config = PluginConfig::default().with_header_style(HeaderStyle::None);
+    let source = generate_state_plugin_source("A", config, Some("a.txt"))
+        .unwrap()
+        .source;
+    assert_that!(source.as_str()).starts_with("use bevy::prelude::AppExtStates;");
+}
+
+#[rstest]
+#[case::full(
+    HeaderStyle::Full,
+    "// generated by bevy_state_plugin_generator v[CARGO_PKG_VERSION]\n"
+)]
+#[case::minimal(HeaderStyle::Minimal, "// generated by bevy_state_plugin_generator\n")]
+#[case::source_hash(HeaderStyle::SourceHash, "// hash: ")]
+#[case::none(HeaderStyle::None, "// bspg:header_style")]
+fn test_render_template_header_style(#[case] header_style: HeaderStyle, #[case] expected: &str) {
+    let template = format!(
+        "// bspg:header_style {}\n// bspg:\n// A\n",
+        header_style.tag()
+    );
+    let rendered = render_template(&template, default()).unwrap();
+    assert_that!(rendered.source.as_str()).starts_with(expected);
+    let rerendered = render_template(&rendered.source, default()).unwrap();
+    assert_that!(rerendered.warnings).is_empty();
+    assert_that!(rerendered.source).is_equal_to(rendered.source);
+}
+
+#[rstest]
+fn test_render_template_hash_protects_the_code(
+    #[values(HeaderStyle::Full, HeaderStyle::Minimal, HeaderStyle::SourceHash)]
+    header_style: HeaderStyle,
+) {
+    let template = format!(
+        "// bspg:header_style {}\n// bspg:\n// A\n",
+        header_style.tag()
+    );
+    let rendered = render_template(&template, default()).unwrap().source;
+    assert_that!(is_modified_by_hand(&rendered)).is_equal_to(false);
+    let edited = format!("{rendered}fn hand_written() {{}}\n");
+    assert_that!(is_modified_by_hand(&edited)).is_equal_to(true);
+}
+
+#[rstest]
+fn test_render_template_with_code_before_the_block() {
+    let template =
+        "use std::fmt;\n\n// bspg:\n// A B\n// bspg:begin generated\n// bspg:end generated\n";
+    let rendered = render_template(template, default()).unwrap().source;
+    assert_that!(rendered.as_str()).starts_with("use std::fmt;\n\n// bspg:\n// A B\n");
+    assert_that!(rendered.as_str()).contains("GameStateA");
+    assert_that!(rendered.as_str()).contains("GameStateB");
+}
+
+#[rstest]
+fn test_render_template_hash_of_each_region(
+    #[values(HeaderStyle::Full, HeaderStyle::SourceHash)] header_style: HeaderStyle,
+    #[values(false, true)] tagged: bool,
+) {
+    let tag = if tagged { " ui" } else { "" };
+    let region = if tagged { "ui" } else { "generated" };
+    let template = formatdoc! {"
+        // bspg:header_style {}
+        // bspg:{tag}
+        // A B
+        // bspg:begin {region}
+        // bspg:end {region}
+
+        fn hand_written() {{}}
+        ",
+        header_style.tag()
+    };
+    let rendered = render_template(&template, default()).unwrap().source;
+    assert_that!(is_modified_by_hand(&rendered)).is_equal_to(false);
+
+    // the states changed, but nobody touched the generated code
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("states.rs");
+    std::fs::write(&path, rendered.replace("// A B\n", "// A B C\n")).unwrap();
+    let outcome = render_template_file(&path, default())
+        .unwrap()
+        .write(&path, false)
+        .unwrap();
+    assert_that!(outcome.changed).is_equal_to(true);
+    let rerendered = std::fs::read_to_string(&path).unwrap();
+    assert_that!(rerendered.as_str()).contains("C,");
+    assert_that!(rerendered.as_str()).ends_with("fn hand_written() {}\n");
+    assert_that!(is_modified_by_hand(&rerendered)).is_equal_to(false);
+
+    let edited = rerendered.replacen("C,", "D,", 1);
+    assert_that!(is_modified_by_hand(&edited)).is_equal_to(true);
+}
+
+#[rstest]
+fn test_generate_from_str_matches_the_generated_file() {
+    let config = PluginConfig::default().with_header_style(HeaderStyle::None);
+    let expected = generate_state_plugin_source("A B { C D }", config.clone(), Some("a.txt"))
+        .unwrap()
+        .source;
+    let source = generate_from_str("A B { C D }", &config).unwrap();
+    assert_that!(source).is_equal_to(expected);
+}
+
+#[rstest]
+fn test_generate_from_str_applies_directives() {
+    let source = generate_from_str("// bspg:plugin_name MyPlugin\nA", &default()).unwrap();
+    assert_that!(source.as_str()).contains("pub struct MyPlugin;");
+    assert_that!(source.as_str()).does_not_contain("bspg:");
+}
+
+#[rstest]
+fn test_generate_with_diagnostics_returns_the_warnings() {
+    let source = "// bspg:plugin_name MyPlugin\n// bspg:nonsense 1\nA";
+    let expected = vec![Diagnostic::new(
+        2,
+        DirectiveError::UnknownSetting("nonsense".to_string()),
+    )];
+    let (generated, warnings) = generate_from_str_with_diagnostics(source, &default()).unwrap();
+    assert_that!(generated.as_str()).contains("pub struct MyPlugin;");
+    assert_that!(warnings).is_equal_to(&expected);
+    let (tokens, warnings) = generate_tokens_with_diagnostics(source, default()).unwrap();
+    assert_that!(tokens.to_string().as_str()).contains("MyPlugin");
+    assert_that!(warnings).is_equal_to(&expected);
+}
+
+#[rstest]
+#[case::unclosed("A { B")]
+#[case::duplicates("A { B } C { B }")]
+fn test_generate_from_str_errors(#[case] source: &str) {
+    let config = PluginConfig::default().with_naming_scheme(NamingScheme::None);
+    assert_that!(generate_from_str(source, &config).is_err()).is_equal_to(true);
+}
+
+#[rstest]
+fn test_parse_states() {
+    let nodes = parse_states("// a comment\nA B { C D }").unwrap();
+    // the public tree owns its names, so it outlives the source
+    assert_that!(nodes).is_equal_to(vec![
+        StateNode::Comment("a comment".to_string()),
+        StateNode::Singleton("A".to_string()),
+        StateNode::Enum(
+            "B".to_string(),
+            vec![
+                StateNode::Singleton("C".to_string()),
+                StateNode::Singleton("D".to_string()),
+            ],
+        ),
+    ]);
+    assert_that!(nodes[2].get_tree_size()).is_equal_to(3);
+    let error = parse_states("A { B").unwrap_err();
+    assert_that!(matches!(error, GeneratorError::Processing { .. })).is_equal_to(true);
+}
+
+#[rstest]
+#[case::source(
+    "// bspg:naming_scheme none\nMenu\nReady { Menu }",
+    generate_from_str,
+    (3, 9),
+    (2, 1)
+)]
+#[case::template(
+    "// bspg:naming_scheme none\n// bspg:\n// Menu\n//   Ready { Menu }\n",
+    |template: &str, config: &PluginConfig| {
+        render_template(template, config.clone()).map(|r| r.source)
+    },
+    (4, 14),
+    (3, 4)
+)]
+fn test_duplicate_name_location(
+    #[case] source: &str,
+    #[case] render: fn(&str, &PluginConfig) -> Result<String, GeneratorError>,
+    #[case] span: (usize, usize),
+    #[case] first_span: (usize, usize),
+) {
+    let error = render(source, &default()).unwrap_err();
+    let GeneratorError::Processing {
+        error: ProcessingError::DuplicateName(duplicate),
+        ..
+    } = &error
+    else {
+        panic!("expected a duplicate name, got {error:?}");
+    };
+    let (second, first) = (duplicate.span.unwrap(), duplicate.first_span.unwrap());
+    assert_that!((second.line, second.column)).is_equal_to(span);
+    assert_that!((first.line, first.column)).is_equal_to(first_span);
+    assert_that!(source.get(second.range())).is_equal_to(Some("Menu"));
+    assert_that!(error.node_path()).is_equal_to(Some("GameState.Ready.Menu"));
+    assert_that!(error.code()).is_equal_to("bspg::duplicate_name");
+}
+
+#[rstest]
+#[case::superscript("A² B", "GameStateA²", "GameState.A²", (1, 1))]
+#[case::keyword("A { Self }", "Self", "GameState.A.Self", (1, 5))]
+fn test_invalid_state_name(
+    #[case] source: &str,
+    #[case] name: &str,
+    #[case] node_path: &str,
+    #[case] location: (usize, usize),
+) {
+    let error = generate_from_str(source, &default()).unwrap_err();
+    let GeneratorError::Processing {
+        error: ProcessingError::InvalidName {
+            name: invalid_name, ..
+        },
+        ..
+    } = &error
+    else {
+        panic!("expected an invalid name, got {error:?}");
+    };
+    assert_that!(invalid_name.as_str()).is_equal_to(name);
+    assert_that!(error.node_path()).is_equal_to(Some(node_path));
+    let span = error.span().unwrap();
+    assert_that!((span.line, span.column)).is_equal_to(location);
+    assert_that!(error.code()).is_equal_to("bspg::invalid_name");
+}
+
+#[rstest]
+#[case::unparsed("// bspg:plugin_name MyPlugin\nA\nB ; C", (3, 3), "; C")]
+#[case::unclosed("A\nB { C", (2, 6), "")]
+fn test_parse_error_location(
+    #[case] source: &str,
+    #[case] location: (usize, usize),
+    #[case] unparsed: &str,
+) {
+    let error = generate_from_str(source, &default()).unwrap_err();
+    let span = error.span().unwrap();
+    assert_that!((span.line, span.column)).is_equal_to(location);
+    assert_that!(error.unparsed_input()).is_equal_to(Some(unparsed));
+    assert_that!(error.code()).is_equal_to("bspg::parse");
+}
+
+#[rstest]
+fn test_errors_name_the_file() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let missing = dir.path().join("missing.txt");
+    let dst = dir.path().join("states.rs");
+    let error = check_plugin(&missing, &dst, default()).unwrap_err();
+    assert_that!(error.path()).is_equal_to(Some(missing.as_path()));
+    assert_that!(error.code()).is_equal_to("bspg::io");
+
+    let src = dir.path().join("states.txt");
+    std::fs::write(&src, "A\nB { C").unwrap();
+    let error = check_plugin(&src, &dst, default()).unwrap_err();
+    assert_that!(error.path()).is_equal_to(Some(src.as_path()));
+    assert_that!(error.to_string())
+        .starts_with(format!("Processing Error: {}:2:6: ", src.display()));
+}
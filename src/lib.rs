@@ -1,19 +1,37 @@
-#![feature(trim_prefix_suffix)]
 #![feature(coverage_attribute)]
 #![warn(missing_docs)]
 #![doc = include_str!("../Readme.md")]
 
+#[cfg(feature = "cli")]
+pub mod cli;
 pub(crate) mod config;
 pub(crate) mod generate;
 pub(crate) mod parsing;
 pub(crate) mod processing;
 #[cfg(test)]
 pub(crate) mod testing;
+#[cfg(feature = "watch")]
+pub mod watch;
 
 /// The types and functions required to use this library
 pub mod prelude {
     pub use std::borrow::Cow;
 
-    pub use crate::config::{NamingScheme, PluginConfig, PluginName};
-    pub use crate::generate::{GeneratorError, generate_plugin, update_template};
+    pub use crate::config::{HeaderStyle, NamingScheme, PluginConfig, PluginName, RustfmtConfig,
+                            RustfmtFailure};
+    pub use crate::generate::core::parse_states;
+    pub use crate::generate::diagnostics::{Diagnostic, DiagnosticError};
+    pub use crate::generate::generator::Generator;
+    pub use crate::generate::output::GenerationOutcome;
+    #[cfg(feature = "fancy-diagnostics")]
+    pub use crate::generate::report::ErrorReport;
+    pub use crate::generate::{GeneratorError, check_plugin, check_template, format_states,
+                              generate_from_str, generate_from_str_with_diagnostics,
+                              generate_plugin, generate_plugin_from_source,
+                              generate_plugin_to_out_dir, generate_tokens,
+                              generate_tokens_with_diagnostics, update_template};
+    pub use crate::parsing::directive::DirectiveError;
+    pub use crate::parsing::span::Span;
+    pub use crate::parsing::tree::StateNode;
+    pub use crate::processing::{DuplicateName, ParseFailure, ProcessingError};
 }
@@ -0,0 +1,210 @@
+#[cfg(test)]
+mod tests;
+
+use nom::branch::alt;
+use nom::bytes::complete::{escaped_transform, is_not, tag, take_while1};
+use nom::character::complete::{char, space0, space1};
+use nom::combinator::{all_consuming, eof, map, opt, recognize, value};
+use nom::multi::separated_list1;
+use nom::sequence::{delimited, preceded, terminated};
+use nom::{IResult, Parser};
+
+/// The value of a `// bspg:name value` directive
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum DirectiveValue {
+    /// A bare word or path, or a quoted string
+    String(String),
+    /// `true` or `false`
+    Bool(bool),
+    /// Values separated by commas, optionally in brackets
+    List(Vec<String>),
+}
+
+impl std::fmt::Display for DirectiveValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::String(value) => write!(f, "{value:?}"),
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::List(values) => write!(f, "[{}]", values.join(", ")),
+        }
+    }
+}
+
+/// Why a `// bspg:` directive was not applied
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum DirectiveError {
+    /// The setting is no [`PluginConfig`](crate::prelude::PluginConfig) field
+    #[error("unknown setting: '{0}'")]
+    UnknownSetting(String),
+    /// The naming scheme is not one of [`NamingScheme`](crate::prelude::NamingScheme)
+    #[error("invalid naming scheme '{0}' (expected [none, short, full])")]
+    InvalidNamingScheme(String),
+    /// The header style is not one of [`HeaderStyle`](crate::prelude::HeaderStyle)
+    #[error("invalid header style '{0}' (expected [full, minimal, source_hash, none])")]
+    InvalidHeaderStyle(String),
+    /// The `rustfmt.on_failure` is not one of [`RustfmtFailure`](crate::prelude::RustfmtFailure)
+    #[error("invalid rustfmt failure '{0}' (expected [ignore, warn, error])")]
+    InvalidRustfmtFailure(String),
+    /// The plugin name is neither `UpperCamelCase` nor `snake_case`
+    #[error("invalid plugin name '{0}' (expected [UpperCamelCase, lower_snake_case])")]
+    InvalidPluginName(String),
+    /// The value has the wrong type for the setting
+    #[error("unexpected value for '{name}': {value}")]
+    UnexpectedValue {
+        /// The setting
+        name: String,
+        /// The value, as written
+        value: String,
+    },
+    /// The line starts with `// bspg:` but cannot be parsed
+    #[error("malformed directive: {0}")]
+    Malformed(String),
+    /// A comment above the directives of a template, which is not kept
+    #[error("dropped line: {0:?}")]
+    DroppedLine(String),
+    /// `// bspg:begin <name>` without a matching `// bspg:end <name>`
+    #[error("`// bspg:begin {0}` is never closed by `// bspg:end {0}`")]
+    UnclosedRegion(String),
+    /// `// bspg:end <name>` without a preceding `// bspg:begin <name>`
+    #[error("`// bspg:end {0}` without a preceding `// bspg:begin {0}`")]
+    UnopenedRegion(String),
+    /// A second `// bspg:begin <name>`
+    #[error("a template can only have one `// bspg:begin {0}` region")]
+    DuplicateRegion(String),
+    /// A region without a `// bspg: <name>` block to generate it from
+    #[error("there is no `// bspg: {0}` block for the `// bspg:begin {0}` region")]
+    UnknownRegion(String),
+    /// A `// bspg:begin <name>` region without a `// bspg:` block above it
+    #[error("there is no `// bspg:` block above the `// bspg:begin {0}` region")]
+    MissingBlock(String),
+    /// A second `// bspg: <tag>` block with the same tag
+    #[error("a template can only have one `// bspg: {0}` block")]
+    DuplicateBlock(String),
+    /// Two blocks of a template generate an item with the same name
+    #[error("'{name}' is generated by both the '{first}' and the '{second}' block")]
+    NameClash {
+        /// The plugin or states module
+        name: String,
+        /// The tag of the block that comes first
+        first: String,
+        /// The tag of the block that comes second
+        second: String,
+    },
+}
+
+/// A comment line that starts with `// bspg:`
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Directive {
+    /// `// bspg:` on its own or `// bspg: tag`, the states follow
+    TemplateStart(Option<String>),
+    /// `// bspg:name value`
+    Setting { name: String, value: DirectiveValue },
+}
+
+/// Settings that are turned on by their name alone, like `// bspg:strict`
+const FLAG_SETTINGS: &[&str] = &["strict", "rustfmt"];
+
+/// Parse a single line: `Ok(None)` if it is no directive at all, `Err` with
+/// the reason if it is a malformed one
+pub(crate) fn parse_directive(line: &str) -> Result<Option<Directive>, String> {
+    let Ok((rest, _)) = directive_prefix(line) else {
+        return Ok(None);
+    };
+    if rest.trim().is_empty() {
+        return Ok(Some(Directive::TemplateStart(None)));
+    }
+    if let Ok((_, tag)) = all_consuming(delimited(space1, word, space0)).parse(rest) {
+        return Ok(Some(Directive::TemplateStart(Some(tag.to_string()))));
+    }
+    let (rest, name) =
+        setting_name(rest).map_err(|_| format!("expected a setting name, found '{rest}'"))?;
+    if rest.trim().is_empty() {
+        if !FLAG_SETTINGS.contains(&name) {
+            return Err(format!("missing value for '{name}'"));
+        }
+        return Ok(Some(Directive::Setting {
+            name: name.to_string(),
+            value: DirectiveValue::Bool(true),
+        }));
+    }
+    let (_, value) = all_consuming(delimited(space1, directive_value, space0))
+        .parse(rest)
+        .map_err(|_| format!("invalid value for '{name}': '{}'", rest.trim()))?;
+    Ok(Some(Directive::Setting {
+        name: name.to_string(),
+        value,
+    }))
+}
+
+fn directive_prefix(input: &str) -> IResult<&str, ()> {
+    value((), (space0, tag("//"), space0, tag("bspg:"))).parse(input)
+}
+
+/// A setting, or a key of a nested one like `rustfmt.edition`
+fn setting_name(input: &str) -> IResult<&str, &str> {
+    recognize(separated_list1(char('.'), word)).parse(input)
+}
+
+fn word(input: &str) -> IResult<&str, &str> {
+    take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_').parse(input)
+}
+
+fn directive_value(input: &str) -> IResult<&str, DirectiveValue> {
+    alt((
+        map(bracketed_list, DirectiveValue::List),
+        map(terminated(boolean, (space0, eof)), DirectiveValue::Bool),
+        map(
+            separated_list1(delimited(space0, char(','), space0), scalar),
+            |mut values| {
+                if values.len() > 1 {
+                    DirectiveValue::List(values)
+                } else {
+                    DirectiveValue::String(values.remove(0))
+                }
+            },
+        ),
+    ))
+    .parse(input)
+}
+
+fn boolean(input: &str) -> IResult<&str, bool> {
+    alt((value(true, tag("true")), value(false, tag("false")))).parse(input)
+}
+
+/// `[a, b, c]`, a trailing comma is allowed
+fn bracketed_list(input: &str) -> IResult<&str, Vec<String>> {
+    delimited(
+        terminated(char('['), space0),
+        terminated(
+            separated_list1(delimited(space0, char(','), space0), scalar),
+            opt(preceded(space0, char(','))),
+        ),
+        preceded(space0, char(']')),
+    )
+    .parse(input)
+}
+
+fn scalar(input: &str) -> IResult<&str, String> {
+    alt((quoted, map(bare_word, String::from))).parse(input)
+}
+
+/// A word, a path like `serde::Serialize` or a file path like `../states.rs`
+fn bare_word(input: &str) -> IResult<&str, &str> {
+    take_while1(|c: char| c.is_alphanumeric() || "_:./-".contains(c)).parse(input)
+}
+
+/// A string in double quotes, `\"` and `\\` are escaped
+fn quoted(input: &str) -> IResult<&str, String> {
+    let contents = escaped_transform(
+        is_not("\\\""),
+        '\\',
+        alt((value("\\", tag("\\")), value("\"", tag("\"")))),
+    );
+    delimited(
+        char('"'),
+        map(opt(contents), Option::unwrap_or_default),
+        char('"'),
+    )
+    .parse(input)
+}
@@ -0,0 +1,96 @@
+use rstest::rstest;
+use speculoos::prelude::*;
+
+use crate::parsing::directive::{Directive, DirectiveValue, parse_directive};
+
+fn setting(name: &str, value: DirectiveValue) -> Option<Directive> {
+    Some(Directive::Setting {
+        name: name.to_string(),
+        value,
+    })
+}
+
+fn string(value: &str) -> DirectiveValue {
+    DirectiveValue::String(value.to_string())
+}
+
+fn list(values: &[&str]) -> DirectiveValue {
+    DirectiveValue::List(values.iter().map(ToString::to_string).collect())
+}
+
+#[rstest]
+#[case::not_a_comment("Loading", None)]
+#[case::other_comment("// generated by bspg", None)]
+#[case::template_start("// bspg:", Some(Directive::TemplateStart(None)))]
+#[case::template_start_spaced("  //  bspg:  ", Some(Directive::TemplateStart(None)))]
+#[case::tagged_template_start(
+    "// bspg: ui",
+    Some(Directive::TemplateStart(Some("ui".to_string())))
+)]
+#[case::tagged_template_start_spaced(
+    "//  bspg:  game_play ",
+    Some(Directive::TemplateStart(Some("game_play".to_string())))
+)]
+#[case::word(
+    "// bspg:naming_scheme short",
+    setting("naming_scheme", string("short"))
+)]
+#[case::quoted(
+    r#"// bspg:root_state_name "Game State""#,
+    setting("root_state_name", string("Game State"))
+)]
+#[case::escaped(r#"// bspg:output "a\"b\\c""#, setting("output", string(r#"a"b\c"#)))]
+#[case::empty_string(r#"// bspg:output """#, setting("output", string("")))]
+#[case::quoted_bool(
+    r#"// bspg:root_state_name "false""#,
+    setting("root_state_name", string("false"))
+)]
+#[case::bool(
+    "// bspg:root_state_name false",
+    setting("root_state_name", DirectiveValue::Bool(false))
+)]
+#[case::path(
+    "// bspg:additional_derives serde::Serialize",
+    setting("additional_derives", string("serde::Serialize"))
+)]
+#[case::file_path(
+    "// bspg:output ../generated/ui.rs",
+    setting("output", string("../generated/ui.rs"))
+)]
+#[case::list(
+    "// bspg:additional_derives PartialOrd, Ord",
+    setting("additional_derives", list(&["PartialOrd", "Ord"]))
+)]
+#[case::bracketed_list(
+    r#"// bspg:additional_derives [PartialOrd, "serde::Serialize",]"#,
+    setting("additional_derives", list(&["PartialOrd", "serde::Serialize"]))
+)]
+#[case::flag("// bspg:strict", setting("strict", DirectiveValue::Bool(true)))]
+#[case::nested(
+    "// bspg:rustfmt.edition 2024",
+    setting("rustfmt.edition", string("2024"))
+)]
+#[case::flag_spaced("  // bspg:rustfmt  ", setting("rustfmt", DirectiveValue::Bool(true)))]
+fn test_parse_directive(#[case] line: &str, #[case] expected: Option<Directive>) {
+    assert_that!(parse_directive(line)).is_ok_containing(expected);
+}
+
+#[rstest]
+#[case::missing_value("// bspg:naming_scheme", "missing value for 'naming_scheme'")]
+#[case::missing_name(
+    "// bspg: naming_scheme short",
+    "expected a setting name, found ' naming_scheme short'"
+)]
+#[case::unclosed_quote(r#"// bspg:plugin_name "MyPlugin"#, "invalid value for 'plugin_name'")]
+#[case::unclosed_list(
+    "// bspg:additional_derives [Ord",
+    "invalid value for 'additional_derives'"
+)]
+#[case::trailing_input(
+    "// bspg:naming_scheme short please",
+    "invalid value for 'naming_scheme'"
+)]
+#[case::no_space(r#"// bspg:plugin_name"MyPlugin""#, "invalid value for 'plugin_name'")]
+fn test_parse_directive_malformed(#[case] line: &str, #[case] reason: &str) {
+    assert_that!(parse_directive(line).unwrap_err()).contains(reason);
+}
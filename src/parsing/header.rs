@@ -2,119 +2,500 @@
 mod tests;
 
 use std::borrow::Cow;
+use std::ops::Range;
+use std::path::PathBuf;
 
 use itertools::Itertools;
-use lazy_regex::regex;
 
+use crate::generate::GeneratorError;
 use crate::generate::core::get_package_info;
-use crate::prelude::{NamingScheme, PluginConfig, PluginName};
+use crate::generate::diagnostics::Diagnostic;
+use crate::parsing::directive::{Directive, DirectiveError, DirectiveValue, parse_directive};
+use crate::parsing::span::{SourceMap, Span};
+use crate::prelude::{HeaderStyle, NamingScheme, PluginConfig, PluginName, RustfmtFailure};
 
 #[derive(Clone, Debug, Default, PartialEq)]
 pub(crate) struct TemplateHeader<'a> {
     pub template: Vec<&'a str>,
     pub comments_block: Vec<&'a str>,
     pub info_block: Vec<String>,
+    pub warnings: Vec<Diagnostic>,
 }
 
-trait Unquote {
-    fn unquoted(&self) -> Self;
+/// A state definition with its leading directives taken out
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct SourceHeader<'a> {
+    /// The directive lines, as written
+    pub directives: Vec<&'a str>,
+    /// Where the plugin should be written, relative to the source
+    pub output: Option<String>,
+    /// The states, without the directive lines
+    pub states: String,
+    /// Problems with the directives, unless they are strict
+    pub warnings: Vec<Diagnostic>,
+    /// Where the lines of the states are in the source
+    pub source_map: SourceMap<'a>,
 }
 
-impl Unquote for &str {
-    fn unquoted(&self) -> Self {
-        self.strip_prefix("\"")
-            .and_then(|s| s.strip_suffix("\""))
-            .unwrap_or(self)
-    }
-}
+/// Directive naming the file the plugin of a standalone source is written to
+pub(crate) const OUTPUT_DIRECTIVE: &str = "output";
 
+/// The fields of [`PluginConfig`] that can be set by a directive. `force` is
+/// not one of them: it guards the file against losing edits made by hand, so
+/// the file itself should not be able to turn that off. `rustfmt` takes a bool,
+/// its other settings are nested, see [`apply_rustfmt_directive`].
 pub(crate) const SUPPORTED_VARIABLES: &[&str] = &[
     "root_state_name",
     "naming_scheme",
     "plugin_name",
     "states_module_name",
     "additional_derives",
+    "strict",
+    "header_style",
+    "rustfmt",
 ];
 
+/// Prefixes of the lines `update_template` writes above the directives itself
+const GENERATED_PREFIXES: &[&str] = &["// generated by ", "// hash: ", "// WARN: "];
+
 pub(crate) fn apply_directive(
     plugin_config: &mut PluginConfig,
     name: &str,
-    value: &str,
-) -> Option<String> {
+    value: &DirectiveValue,
+) -> Result<(), DirectiveError> {
+    if let Some(key) = name.strip_prefix("rustfmt.") {
+        return apply_rustfmt_directive(plugin_config, key, value);
+    }
     if !SUPPORTED_VARIABLES.contains(&name) {
-        return Some(format!("unknown setting: '{name}'"));
+        return Err(DirectiveError::UnknownSetting(name.to_string()));
     }
-    match name {
-        "root_state_name" => {
-            plugin_config.root_state_name = if value == "None" {
-                None
-            } else {
-                Some(Cow::Owned(value.unquoted().to_string()))
-            };
+    match (name, value) {
+        // `None` is kept for templates written before booleans were supported
+        ("root_state_name", DirectiveValue::Bool(false)) => {
+            plugin_config.root_state_name = None;
         }
-        "naming_scheme" => {
-            if let Some(naming_scheme) = NamingScheme::try_parse(value) {
-                plugin_config.naming_scheme = naming_scheme;
-            } else {
-                return Some(format!(
-                    "invalid naming scheme '{value}' (expected [none, short, full])"
-                ));
-            }
+        ("root_state_name", DirectiveValue::String(value)) if value == "None" => {
+            plugin_config.root_state_name = None;
         }
-        "plugin_name" => {
-            if let Some(plugin_name) = PluginName::parse(value) {
-                let name = (*plugin_name).to_owned();
-                plugin_config.plugin_name = PluginName::parse(name).unwrap();
-            } else {
-                return Some(format!(
-                    "invalid plugin name '{value}' (expected [UpperCamelCase, lower_snake_case])"
-                ));
-            }
+        ("root_state_name", DirectiveValue::String(value)) => {
+            plugin_config.root_state_name = Some(Cow::Owned(value.clone()));
+        }
+        ("naming_scheme", DirectiveValue::String(value)) => {
+            plugin_config.naming_scheme = NamingScheme::try_parse(value)
+                .ok_or_else(|| DirectiveError::InvalidNamingScheme(value.clone()))?;
+        }
+        ("plugin_name", DirectiveValue::String(value)) => {
+            plugin_config.plugin_name = PluginName::parse(value.clone())
+                .ok_or_else(|| DirectiveError::InvalidPluginName(value.clone()))?;
         }
-        "states_module_name" => {
-            plugin_config.states_module_name = Cow::from(value.to_string());
+        ("states_module_name", DirectiveValue::String(value)) => {
+            plugin_config.states_module_name = Cow::Owned(value.clone());
         }
-        "additional_derives" => {
-            let mut to_add = value
-                .split_terminator(",")
-                .map(ToString::to_string)
-                .map(Cow::Owned)
-                .collect_vec();
+        ("additional_derives", DirectiveValue::String(value)) => {
+            plugin_config
+                .additional_derives
+                .push(Cow::Owned(value.clone()));
+        }
+        ("additional_derives", DirectiveValue::List(values)) => {
+            let mut to_add = values.iter().cloned().map(Cow::Owned).collect_vec();
             plugin_config.additional_derives.append(&mut to_add);
         }
-        _ => {
-            unreachable!()
+        ("header_style", DirectiveValue::String(value)) => {
+            plugin_config.header_style = HeaderStyle::try_parse(value)
+                .ok_or_else(|| DirectiveError::InvalidHeaderStyle(value.clone()))?;
+        }
+        ("strict", DirectiveValue::Bool(strict)) => {
+            plugin_config.strict = *strict;
+        }
+        // only switches it, the other settings are up to the caller
+        ("rustfmt", DirectiveValue::Bool(enabled)) => {
+            plugin_config.rustfmt.enabled = *enabled;
+        }
+        (name, value) => {
+            return Err(DirectiveError::UnexpectedValue {
+                name: name.to_string(),
+                value: value.to_string(),
+            });
         }
     }
-    None
+    Ok(())
 }
 
-pub(crate) fn parse_template_header<'a>(
+/// `// bspg:rustfmt.<key> value`: `enabled`, `edition`, `config_path` and
+/// `on_failure` set those of [`RustfmtConfig`](crate::prelude::RustfmtConfig),
+/// any other key is passed as a rustfmt option, like `// bspg:rustfmt.max_width 80`
+fn apply_rustfmt_directive(
+    plugin_config: &mut PluginConfig,
+    key: &str,
+    value: &DirectiveValue,
+) -> Result<(), DirectiveError> {
+    let rustfmt = &mut plugin_config.rustfmt;
+    match (key, value) {
+        ("enabled", DirectiveValue::Bool(enabled)) => {
+            rustfmt.enabled = *enabled;
+        }
+        ("edition", DirectiveValue::String(edition)) => {
+            rustfmt.edition = Some(Cow::Owned(edition.clone()));
+        }
+        ("config_path", DirectiveValue::String(path)) => {
+            rustfmt.config_path = Some(PathBuf::from(path));
+        }
+        ("on_failure", DirectiveValue::String(on_failure)) => {
+            rustfmt.on_failure = RustfmtFailure::try_parse(on_failure)
+                .ok_or_else(|| DirectiveError::InvalidRustfmtFailure(on_failure.clone()))?;
+        }
+        ("enabled" | "edition" | "config_path" | "on_failure", _)
+        | (_, DirectiveValue::List(_)) => {
+            return Err(DirectiveError::UnexpectedValue {
+                name: format!("rustfmt.{key}"),
+                value: value.to_string(),
+            });
+        }
+        (option, DirectiveValue::String(value)) => {
+            let option = (Cow::Owned(option.to_string()), Cow::Owned(value.clone()));
+            rustfmt.options.push(option);
+        }
+        (option, DirectiveValue::Bool(value)) => {
+            let option = (
+                Cow::Owned(option.to_string()),
+                Cow::Owned(value.to_string()),
+            );
+            rustfmt.options.push(option);
+        }
+    }
+    Ok(())
+}
+
+/// Fail on the first issue in strict mode, otherwise turn them into warnings
+fn handle_issues(
+    source: &str,
+    issues: Vec<(usize, DirectiveError)>,
+    plugin_config: &PluginConfig,
+) -> Result<Vec<Diagnostic>, GeneratorError> {
+    let mut issues = issues.into_iter();
+    if plugin_config.strict
+        && let Some((line, error)) = issues.next()
+    {
+        return Err(directive_error(source, line, error));
+    }
+    Ok(issues
+        .map(|(line, error)| Diagnostic::new(line, error))
+        .collect())
+}
+
+/// The directive on `line` of `source` is wrong
+pub(crate) fn directive_error(source: &str, line: usize, error: DirectiveError) -> GeneratorError {
+    GeneratorError::Directive {
+        path: None,
+        span: Span::of_line(source, line),
+        error: Box::new(error),
+    }
+}
+
+/// Apply the `// bspg:name value` directives at the top of a standalone state
+/// definition. Other leading comments are kept, they document the first state.
+pub(crate) fn parse_source_header<'a>(
     source: &'a str,
     plugin_config: &mut PluginConfig,
-) -> TemplateHeader<'a> {
-    let leading_comments = source
-        .lines()
-        .take_while(|line| line.starts_with("//"))
-        .collect_vec();
+) -> Result<SourceHeader<'a>, GeneratorError> {
+    let mut header = SourceHeader::default();
+    let mut states = Vec::new();
+    let mut issues = Vec::new();
+    let mut in_header = true;
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        in_header &= line.trim().is_empty() || line.trim_start().starts_with("//");
+        let directive = if in_header {
+            parse_directive(line.trim_end())
+        } else {
+            Ok(None)
+        };
+        let line_number = index + 1;
+        match directive {
+            Ok(Some(Directive::Setting { name, value })) => {
+                header.directives.push(line.trim_end());
+                let result = match (name.as_str(), value) {
+                    (OUTPUT_DIRECTIVE, DirectiveValue::String(output)) => {
+                        header.output = Some(output);
+                        Ok(())
+                    }
+                    (OUTPUT_DIRECTIVE, value) => Err(DirectiveError::UnexpectedValue {
+                        name,
+                        value: value.to_string(),
+                    }),
+                    (name, value) => apply_directive(plugin_config, name, &value),
+                };
+                if let Err(error) = result {
+                    issues.push((line_number, error));
+                }
+            }
+            Ok(Some(Directive::TemplateStart(_))) | Ok(None) => states.push(line),
+            Err(reason) => {
+                issues.push((line_number, DirectiveError::Malformed(reason)));
+                states.push(line);
+            }
+        }
+    }
+    header.warnings = handle_issues(source, issues, plugin_config)?;
+    header.states = states.concat();
+    header.source_map = SourceMap::from_lines(source, &states, 0);
+    Ok(header)
+}
 
-    let mut info_block = Vec::new();
-    info_block.push(format!("generated by {}", get_package_info()));
+/// The lines around the generated part of a template:
+/// `// bspg:begin <name>` and `// bspg:end <name>`
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RegionMarker {
+    Begin,
+    End,
+}
+
+fn region_marker(line: &str) -> Option<(RegionMarker, String)> {
+    match parse_directive(line.trim_end()) {
+        Ok(Some(Directive::Setting {
+            name,
+            value: DirectiveValue::String(value),
+        })) => match name.as_str() {
+            "begin" => Some((RegionMarker::Begin, value)),
+            "end" => Some((RegionMarker::End, value)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The name of the region of the block started by `// bspg:` on its own
+pub(crate) const DEFAULT_REGION: &str = "generated";
 
-    macro_rules! emit_warning {
-        ($($exprs:expr),*) => {
-            let warning = format!($($exprs),*);
-            let warning = format!("WARN: {warning}");
-            eprintln!("{warning}");
-            info_block.push(warning);
+/// The part of a template between `// bspg:begin <name>` and `// bspg:end <name>`.
+/// Everything outside of it is left untouched.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct GeneratedRegion {
+    /// The tag of the block it is generated from, `generated` if it has none
+    pub name: String,
+    /// The line of `// bspg:begin <name>`, starting at 1
+    pub line: usize,
+    /// The bytes between the marker lines
+    pub range: Range<usize>,
+}
+
+/// All generated regions of a template, in order
+pub(crate) fn find_generated_regions(source: &str) -> Result<Vec<GeneratedRegion>, GeneratorError> {
+    let mut open: Option<(String, usize, usize)> = None;
+    let mut regions: Vec<GeneratedRegion> = Vec::new();
+    let mut offset = 0;
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        let line_number = index + 1;
+        let line_start = offset;
+        offset += line.len();
+        let Some((marker, name)) = region_marker(line) else {
+            continue;
+        };
+        let error = match (marker, open.take()) {
+            (RegionMarker::Begin, Some((open_name, begin_line, _))) => {
+                Some((begin_line, DirectiveError::UnclosedRegion(open_name)))
+            }
+            (RegionMarker::Begin, None) if regions.iter().any(|region| region.name == name) => {
+                Some((line_number, DirectiveError::DuplicateRegion(name)))
+            }
+            (RegionMarker::Begin, None) => {
+                open = Some((name, line_number, offset));
+                None
+            }
+            (RegionMarker::End, Some((open_name, line, start))) if open_name == name => {
+                regions.push(GeneratedRegion {
+                    name,
+                    line,
+                    range: start..line_start,
+                });
+                None
+            }
+            (RegionMarker::End, Some((open_name, begin_line, _))) => {
+                Some((begin_line, DirectiveError::UnclosedRegion(open_name)))
+            }
+            (RegionMarker::End, None) => Some((line_number, DirectiveError::UnopenedRegion(name))),
         };
+        if let Some((line, error)) = error {
+            return Err(directive_error(source, line, error));
+        }
+    }
+    match open {
+        Some((name, line, _)) => Err(directive_error(
+            source,
+            line,
+            DirectiveError::UnclosedRegion(name),
+        )),
+        None => Ok(regions),
+    }
+}
+
+/// The first lines of a generated template, and the warnings about the
+/// directives. The hash of [`HeaderStyle::SourceHash`] is added once the code
+/// is generated.
+fn info_block(header_style: HeaderStyle, warnings: &[Diagnostic]) -> Vec<String> {
+    let generated_by = match header_style {
+        HeaderStyle::Full => Some(format!("generated by {}", get_package_info())),
+        HeaderStyle::Minimal => Some(format!("generated by {}", env!("CARGO_PKG_NAME"))),
+        HeaderStyle::SourceHash | HeaderStyle::None => None,
+    };
+    let warnings = warnings.iter().map(|warning| format!("WARN: {warning}"));
+    generated_by.into_iter().chain(warnings).collect()
+}
+
+/// A `// bspg: <tag>` block of a template with several of them
+#[derive(Clone, Debug)]
+pub(crate) struct TemplateBlock<'a> {
+    /// The tag, `generated` for a block started by `// bspg:` on its own
+    pub tag: String,
+    /// The line of `// bspg: <tag>`, starting at 1
+    pub line: usize,
+    pub template: Vec<&'a str>,
+    /// The configuration passed in, with the directives above the block applied
+    pub config: PluginConfig,
+    pub info_block: Vec<String>,
+    pub warnings: Vec<Diagnostic>,
+    /// The byte after the last line of the block, where its region is
+    /// inserted if the template does not have one yet
+    pub end: usize,
+}
+
+/// The lines of `source` that are not inside of one of the `regions`,
+/// with their line number and the byte after them
+fn lines_outside<'a>(
+    source: &'a str,
+    regions: &'a [GeneratedRegion],
+) -> impl Iterator<Item = (usize, &'a str, usize)> + 'a {
+    source
+        .split_inclusive('\n')
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len();
+            Some((start, line, *offset))
+        })
+        .enumerate()
+        .filter(|(_, (start, ..))| !regions.iter().any(|region| region.range.contains(start)))
+        .map(|(index, (_, line, end))| (index + 1, line, end))
+}
+
+/// Whether the template has a `// bspg: <tag>` block, outside of the generated regions
+pub(crate) fn has_tagged_blocks(source: &str, regions: &[GeneratedRegion]) -> bool {
+    lines_outside(source, regions).any(|(_, line, _)| {
+        matches!(
+            parse_directive(line.trim_end()),
+            Ok(Some(Directive::TemplateStart(Some(_))))
+        )
+    })
+}
+
+/// Split a template into its `// bspg: <tag>` blocks. The directives in the
+/// comments above a block apply to that block only, other comments are kept.
+pub(crate) fn parse_template_blocks<'a>(
+    source: &'a str,
+    plugin_config: &PluginConfig,
+    regions: &'a [GeneratedRegion],
+) -> Result<Vec<TemplateBlock<'a>>, GeneratorError> {
+    let mut blocks: Vec<TemplateBlock> = Vec::new();
+    // the directives of the comments so far apply to the next block
+    let mut config = plugin_config.clone();
+    let mut issues = Vec::new();
+    let mut current: Option<TemplateBlock> = None;
+    for (line_number, line, end) in lines_outside(source, regions) {
+        let comment = line.trim_start().strip_prefix("//");
+        if comment.is_none() || region_marker(line).is_some() {
+            blocks.extend(current.take());
+            config = plugin_config.clone();
+            issues.clear();
+            continue;
+        }
+        match (parse_directive(line.trim_end()), &mut current) {
+            (Ok(Some(Directive::TemplateStart(tag))), _) => {
+                blocks.extend(current.take());
+                let tag = tag.unwrap_or_else(|| DEFAULT_REGION.to_string());
+                if blocks.iter().any(|block| block.tag == tag) {
+                    return Err(directive_error(
+                        source,
+                        line_number,
+                        DirectiveError::DuplicateBlock(tag),
+                    ));
+                }
+                let config = std::mem::replace(&mut config, plugin_config.clone());
+                let warnings = handle_issues(source, std::mem::take(&mut issues), &config)?;
+                current = Some(TemplateBlock {
+                    tag,
+                    line: line_number,
+                    template: Vec::new(),
+                    info_block: info_block(config.header_style, &warnings),
+                    config,
+                    warnings,
+                    end,
+                });
+            }
+            (_, Some(block)) => {
+                block.template.extend(comment.map(str::trim));
+                block.end = end;
+            }
+            (Ok(Some(Directive::Setting { name, value })), None) => {
+                if let Err(error) = apply_directive(&mut config, &name, &value) {
+                    issues.push((line_number, error));
+                }
+            }
+            (Err(reason), None) => issues.push((line_number, DirectiveError::Malformed(reason))),
+            (Ok(None), None) => {}
+        }
     }
+    blocks.extend(current);
+    Ok(blocks)
+}
+
+/// The comment lines of the `// bspg:` block with the index of the first one.
+/// Without a region they are at the top of the template, with one they are
+/// the last block above it, so there can be hand-written code before them.
+fn header_lines<'a>(
+    source: &'a str,
+    region: Option<&GeneratedRegion>,
+) -> Result<(usize, Vec<&'a str>), GeneratorError> {
+    let is_comment = |line: &&str| line.starts_with("//") && region_marker(line).is_none();
+    let Some(region) = region else {
+        return Ok((0, source.lines().take_while(is_comment).collect_vec()));
+    };
+    let lines = source.lines().take(region.line - 1).collect_vec();
+    let Some(start) = lines.iter().rposition(|line| {
+        matches!(
+            parse_directive(line),
+            Ok(Some(Directive::TemplateStart(None)))
+        )
+    }) else {
+        let error = DirectiveError::MissingBlock(region.name.clone());
+        return Err(directive_error(source, region.line, error));
+    };
+    let first = lines[..start]
+        .iter()
+        .rposition(|line| !is_comment(line))
+        .map_or(0, |index| index + 1);
+    let end = lines[start..]
+        .iter()
+        .position(|line| !is_comment(line))
+        .map_or(lines.len(), |index| start + index);
+    Ok((first, lines[first..end].to_vec()))
+}
+
+/// Read the comments of the `// bspg:` block of a template. With a generated
+/// region they are kept as they are, otherwise only the directives survive.
+pub(crate) fn parse_template_header<'a>(
+    source: &'a str,
+    plugin_config: &mut PluginConfig,
+    region: Option<&GeneratedRegion>,
+) -> Result<TemplateHeader<'a>, GeneratorError> {
+    let has_region = region.is_some();
+    let (first, leading_comments) = header_lines(source, region)?;
 
+    let mut issues = Vec::new();
     let mut template_source = Vec::new();
     let mut in_template = false;
     let leading_comments = leading_comments
         .iter()
-        .filter(|line| {
+        .enumerate()
+        .filter(|(index, line)| {
+            let line_number = first + index + 1;
             if in_template {
                 if let Some(line) = line.strip_prefix("//") {
                     template_source.push(line.trim());
@@ -122,27 +503,45 @@ pub(crate) fn parse_template_header<'a>(
                 } else {
                     false
                 }
-            } else if let Some(captures) =
-                regex!(r#"^\s*//\s*bspg:(\w+)\s+(\w+|"\w+\")\s*$"#).captures(line)
-            {
-                let (_, [name, value]) = captures.extract();
-                if let Some(warning) = apply_directive(plugin_config, name, value) {
-                    emit_warning!("{warning}");
-                }
-                true
-            } else if regex!(r#"^\s*//\s*bspg:\s*$"#).is_match(line) {
-                in_template = true;
-                true
             } else {
-                eprintln!("dropping: '{line:?}'");
-                false
+                match parse_directive(line) {
+                    Ok(Some(Directive::Setting { name, value })) => {
+                        if let Err(error) = apply_directive(plugin_config, &name, &value) {
+                            issues.push((line_number, error));
+                        }
+                        true
+                    }
+                    Ok(Some(Directive::TemplateStart(_))) => {
+                        in_template = true;
+                        true
+                    }
+                    // keep malformed directives, so they can be fixed
+                    Err(reason) => {
+                        issues.push((line_number, DirectiveError::Malformed(reason)));
+                        true
+                    }
+                    Ok(None) if has_region => true,
+                    Ok(None) => {
+                        let generated = GENERATED_PREFIXES
+                            .iter()
+                            .any(|prefix| line.starts_with(prefix));
+                        if !generated {
+                            issues
+                                .push((line_number, DirectiveError::DroppedLine(line.to_string())));
+                        }
+                        false
+                    }
+                }
             }
         })
-        .copied()
+        .map(|(_, line)| *line)
         .collect_vec();
-    TemplateHeader {
+
+    let warnings = handle_issues(source, issues, plugin_config)?;
+    Ok(TemplateHeader {
         template: template_source,
         comments_block: leading_comments,
-        info_block,
-    }
+        info_block: info_block(plugin_config.header_style, &warnings),
+        warnings,
+    })
 }
@@ -6,11 +6,19 @@ use indoc::formatdoc;
 use insta::assert_snapshot;
 use itertools::Itertools;
 use rstest::rstest;
-use speculoos::assert_that;
-use speculoos::prelude::{ContainingIntoIterAssertions, OptionAssertions, VecAssertions};
+use speculoos::prelude::*;
 
-use crate::parsing::header::{SUPPORTED_VARIABLES, apply_directive, parse_template_header};
-use crate::prelude::{NamingScheme, PluginConfig, PluginName};
+use crate::generate::GeneratorError;
+use crate::parsing::directive::{DirectiveError, DirectiveValue};
+use crate::parsing::header::{DEFAULT_REGION, GeneratedRegion, SUPPORTED_VARIABLES,
+                             apply_directive, find_generated_regions, has_tagged_blocks,
+                             parse_source_header, parse_template_blocks, parse_template_header};
+use crate::prelude::{HeaderStyle, NamingScheme, PluginConfig, PluginName, RustfmtConfig,
+                     RustfmtFailure};
+
+fn string(value: &str) -> DirectiveValue {
+    DirectiveValue::String(value.to_string())
+}
 
 #[rstest]
 #[case(String::new())]
@@ -20,7 +28,7 @@ use crate::prelude::{NamingScheme, PluginConfig, PluginName};
 "})]
 fn test_parse_template_header(#[case] header: String) {
     let mut config: PluginConfig = default();
-    let header = parse_template_header(&header, &mut config);
+    let header = parse_template_header(&header, &mut config, None).unwrap();
     assert_that!(header.template).is_empty();
     assert_that!(header.comments_block).is_empty();
 }
@@ -33,11 +41,11 @@ fn test_parse_template_header_keep_variables() {
         // bspg:some setting
     "};
     let mut config: PluginConfig = default();
-    let header = parse_template_header(&header, &mut config);
+    let header = parse_template_header(&header, &mut config, None).unwrap();
     assert_that!(header.comments_block)
         .is_equal_to(header.comments_block.iter().copied().unique().collect_vec());
     assert_that!(header.comments_block).contains("// bspg:some setting");
-    assert_that!(header.info_block).contains("WARN: unknown setting: 'some'".to_string());
+    assert_that!(header.info_block).contains("WARN: line 3: unknown setting: 'some'".to_string());
 }
 
 #[rstest]
@@ -56,7 +64,7 @@ fn test_parse_template_header_variable_values(
         name.map(|name| format!("\"{name}\"")).unwrap_or("None".to_string()),
         naming_scheme
     };
-    let _ = parse_template_header(&header, &mut config);
+    let _ = parse_template_header(&header, &mut config, None).unwrap();
     assert_that!(config.root_state_name.as_ref().map(ToString::to_string))
         .is_equal_to(name.map(String::from));
     assert_that!(config.naming_scheme).is_equal_to(naming_scheme);
@@ -64,16 +72,78 @@ fn test_parse_template_header_variable_values(
 
 #[rstest]
 fn test_plugin_config_all_fields_supported_as_variables() {
+    // set by the caller only, see `SUPPORTED_VARIABLES`
+    let not_variables = ["force"];
     let config = PluginConfig::default();
     let info = config.get_represented_struct_info().unwrap();
-    assert_that!(info.field_names().to_vec()).contains_all_of(&SUPPORTED_VARIABLES);
+    let fields = info.field_names().to_vec();
+    assert_that!(fields).contains_all_of(&SUPPORTED_VARIABLES);
+    assert_that!(fields).contains_all_of(&not_variables.iter());
+    let expected = SUPPORTED_VARIABLES.iter().chain(&not_variables);
+    assert_that!(fields.len()).is_equal_to(expected.count());
+}
+
+#[rstest]
+#[case::off(false)]
+#[case::on(true)]
+fn test_parse_template_header_rustfmt(#[case] enabled: bool) {
+    let mut plugin_config =
+        PluginConfig::default().with_rustfmt(RustfmtConfig::new().with_enabled(!enabled));
+    let result = apply_directive(
+        &mut plugin_config,
+        "rustfmt",
+        &DirectiveValue::Bool(enabled),
+    );
+    assert_that!(result).is_ok();
+    assert_that!(plugin_config.rustfmt.enabled).is_equal_to(enabled);
+}
+
+#[rstest]
+fn test_parse_template_header_rustfmt_settings() {
+    let template = formatdoc! {r#"
+        // bspg:rustfmt.edition 2024
+        // bspg:rustfmt.config_path ../rustfmt.toml
+        // bspg:rustfmt.on_failure warn
+        // bspg:rustfmt.max_width 80
+        // bspg:rustfmt.reorder_imports false
+        // bspg:
+        // Loading
+    "#};
+    let mut config = PluginConfig::default();
+    let header = parse_template_header(&template, &mut config, None).unwrap();
+    assert_that!(header.warnings).is_empty();
+    let expected = RustfmtConfig::new()
+        .with_edition("2024")
+        .with_config_path("../rustfmt.toml")
+        .with_on_failure(RustfmtFailure::Warn)
+        .with_option("max_width", 80)
+        .with_option("reorder_imports", false);
+    assert_that!(config.rustfmt).is_equal_to(expected);
+}
+
+#[rstest]
+#[case::invalid_failure("rustfmt.on_failure", string("panic"), "invalid rustfmt failure")]
+#[case::bool_edition("rustfmt.edition", DirectiveValue::Bool(true), "unexpected value")]
+#[case::list_option(
+    "rustfmt.max_width",
+    DirectiveValue::List(vec!["80".to_string()]),
+    "unexpected value"
+)]
+fn test_apply_directive_rustfmt_errors(
+    #[case] name: &str,
+    #[case] value: DirectiveValue,
+    #[case] reason: &str,
+) {
+    let mut plugin_config = PluginConfig::default();
+    let result = apply_directive(&mut plugin_config, name, &value);
+    assert_that!(result.unwrap_err().to_string()).starts_with(reason);
 }
 
 #[rstest]
 fn test_parse_template_header_root_state_name() {
     let mut plugin_config = PluginConfig::default();
-    let warning = apply_directive(&mut plugin_config, "root_state_name", "Bob");
-    assert_that!(warning).is_none();
+    let result = apply_directive(&mut plugin_config, "root_state_name", &string("Bob"));
+    assert_that!(result).is_ok();
     assert_that!(
         plugin_config
             .root_state_name
@@ -87,8 +157,8 @@ fn test_parse_template_header_root_state_name() {
 #[rstest]
 fn test_parse_template_header_states_module_name() {
     let mut plugin_config = PluginConfig::default();
-    let warning = apply_directive(&mut plugin_config, "states_module_name", "Bob");
-    assert_that!(warning).is_none();
+    let result = apply_directive(&mut plugin_config, "states_module_name", &string("Bob"));
+    assert_that!(result).is_ok();
     assert_that!(plugin_config.states_module_name.as_ref()).is_equal_to("Bob");
 }
 
@@ -106,22 +176,46 @@ fn test_parse_template_header_naming_scheme(
     #[case] expected_warning: bool,
 ) {
     let mut plugin_config = PluginConfig::default();
-    let warning = apply_directive(&mut plugin_config, "naming_scheme", input);
+    let result = apply_directive(&mut plugin_config, "naming_scheme", &string(input));
     if expected_warning {
         assert_snapshot!(
-            warning.unwrap(),
+            result.unwrap_err(),
             @"invalid naming scheme 'invalid input' (expected [none, short, full])");
     } else {
-        assert_that!(warning).is_none();
+        assert_that!(result).is_ok();
     };
     assert_that!(plugin_config.naming_scheme).is_equal_to(expected);
 }
 
+#[rstest]
+#[case("full", Some(HeaderStyle::Full))]
+#[case("minimal", Some(HeaderStyle::Minimal))]
+#[case("source_hash", Some(HeaderStyle::SourceHash))]
+#[case("none", Some(HeaderStyle::None))]
+#[case("Minimal", None)]
+fn test_apply_directive_header_style(#[case] input: &str, #[case] expected: Option<HeaderStyle>) {
+    let mut plugin_config = PluginConfig::default();
+    let result = apply_directive(&mut plugin_config, "header_style", &string(input));
+    match expected {
+        Some(expected) => {
+            assert_that!(result).is_ok();
+            assert_that!(plugin_config.header_style).is_equal_to(expected);
+        }
+        None => assert_snapshot!(
+            result.unwrap_err(),
+            @"invalid header style 'Minimal' (expected [full, minimal, source_hash, none])"),
+    }
+}
+
 #[rstest]
 fn test_parse_template_header_additional_derives() {
     let mut plugin_config = PluginConfig::default();
-    let warning = apply_directive(&mut plugin_config, "additional_derives", "Alice,Bob");
-    assert_that!(warning).is_none();
+    let result = apply_directive(
+        &mut plugin_config,
+        "additional_derives",
+        &DirectiveValue::List(vec!["Alice".to_string(), "Bob".to_string()]),
+    );
+    assert_that!(result).is_ok();
     assert_that!(plugin_config.additional_derives).contains_all_of(&[
         &Cow::from("Alice".to_string()),
         &Cow::from("Bob".to_string()),
@@ -136,7 +230,325 @@ fn test_parse_template_header_plugin_name(
     #[case] expected_plugin_name: PluginName,
 ) {
     let mut plugin_config = PluginConfig::default();
-    let warning = apply_directive(&mut plugin_config, "plugin_name", plugin_name);
-    assert_that!(warning).is_none();
+    let result = apply_directive(&mut plugin_config, "plugin_name", &string(plugin_name));
+    assert_that!(result).is_ok();
     assert_that!(plugin_config.plugin_name).is_equal_to(expected_plugin_name);
 }
+
+#[rstest]
+fn test_parse_source_header() {
+    let source = formatdoc! {r#"
+        // bspg:naming_scheme short
+        // bspg:output "../generated/ui states.rs"
+
+        // the first state
+        // bspg:plugin_name ui_plugin
+        Hud
+        // bspg:root_state_name Ignored
+        Menu
+    "#};
+    let mut config: PluginConfig = default();
+    let header = parse_source_header(&source, &mut config).unwrap();
+    assert_that!(header.output).is_equal_to(Some("../generated/ui states.rs".to_string()));
+    assert_that!(header.directives).has_length(3);
+    assert_snapshot!(header.states, @r"
+    // the first state
+    Hud
+    // bspg:root_state_name Ignored
+    Menu
+    ");
+    assert_that!(config.naming_scheme).is_equal_to(NamingScheme::Short);
+    assert_that!(config.plugin_name).is_equal_to(PluginName::new_function("ui_plugin"));
+    assert_that!(config.root_state_name).is_equal_to(Some(Cow::from("GameState")));
+}
+
+#[rstest]
+#[case::bool(DirectiveValue::Bool(false), None)]
+#[case::legacy_none(string("None"), None)]
+#[case::name(string("Root"), Some("Root"))]
+fn test_apply_directive_root_state_name(
+    #[case] value: DirectiveValue,
+    #[case] expected: Option<&str>,
+) {
+    let mut plugin_config = PluginConfig::default();
+    assert_that!(apply_directive(
+        &mut plugin_config,
+        "root_state_name",
+        &value
+    ))
+    .is_ok();
+    assert_that!(plugin_config.root_state_name).is_equal_to(expected.map(Cow::from));
+}
+
+#[rstest]
+#[case::bool_name("plugin_name", DirectiveValue::Bool(true))]
+#[case::list_scheme("naming_scheme", DirectiveValue::List(vec!["short".to_string()]))]
+fn test_apply_directive_unexpected_value(#[case] name: &str, #[case] value: DirectiveValue) {
+    let mut plugin_config = PluginConfig::default();
+    let result = apply_directive(&mut plugin_config, name, &value);
+    assert_that!(result.unwrap_err().to_string())
+        .starts_with(format!("unexpected value for '{name}'"));
+}
+
+#[rstest]
+fn test_parse_template_header_reports_malformed_directives() {
+    let template = formatdoc! {r#"
+        // bspg:additional_derives [PartialOrd, serde::Serialize]
+        // bspg:plugin_name "unclosed
+        // bspg:
+        // Loading
+    "#};
+    let mut config: PluginConfig = default();
+    let header = parse_template_header(&template, &mut config, None).unwrap();
+    assert_that!(header.comments_block).contains(r#"// bspg:plugin_name "unclosed"#);
+    assert_that!(header.info_block).contains(
+        "WARN: line 2: malformed directive: invalid value for 'plugin_name': '\"unclosed'"
+            .to_string(),
+    );
+    assert_that!(config.additional_derives)
+        .is_equal_to(vec![Cow::from("PartialOrd"), Cow::from("serde::Serialize")]);
+    assert_that!(header.template).is_equal_to(vec!["Loading"]);
+}
+
+#[rstest]
+#[case::unknown_setting(
+    "// bspg:nonsense 123\n// bspg:\n// A",
+    1,
+    DirectiveError::UnknownSetting("nonsense".to_string())
+)]
+#[case::naming_scheme(
+    "// bspg:naming_scheme long\n// bspg:\n// A",
+    1,
+    DirectiveError::InvalidNamingScheme("long".to_string())
+)]
+#[case::dropped_line(
+    "// generated by v[CARGO_PKG_VERSION]\n// a note\n// bspg:\n// A",
+    2,
+    DirectiveError::DroppedLine("// a note".to_string())
+)]
+#[case::malformed(
+    "// bspg:plugin_name \"MyPlugin\n// bspg:\n// A",
+    1,
+    DirectiveError::Malformed("invalid value for 'plugin_name': '\"MyPlugin'".to_string())
+)]
+fn test_parse_template_header_strict(
+    #[case] template: &str,
+    #[case] expected_line: usize,
+    #[case] expected_error: DirectiveError,
+) {
+    let mut lenient = PluginConfig::default();
+    let header = parse_template_header(template, &mut lenient, None).unwrap();
+    assert_that!(header.info_block).has_length(2);
+
+    let mut strict = PluginConfig::default().with_strict(true);
+    let error = parse_template_header(template, &mut strict, None).unwrap_err();
+    let GeneratorError::Directive { span, error, .. } = error else {
+        panic!("expected a directive error, got {error:?}");
+    };
+    assert_that!(span.line).is_equal_to(expected_line);
+    assert_that!(*error).is_equal_to(expected_error);
+}
+
+#[rstest]
+#[case::with_value("// bspg:strict true")]
+#[case::bare("// bspg:strict")]
+fn test_parse_source_header_strict_directive(#[case] strict: &str) {
+    let source = format!("// bspg:nonsense 123\n{strict}\nA");
+    let source = source.as_str();
+    let error = parse_source_header(source, &mut default()).unwrap_err();
+    assert_that!(error.to_string())
+        .is_equal_to("Directive Error on line 1: unknown setting: 'nonsense'".to_string());
+    let span = error.span().unwrap();
+    assert_that!(source.get(span.range())).is_equal_to(Some("// bspg:nonsense 123"));
+}
+
+/// The `generated` region, starting on `line`
+fn region(line: usize) -> GeneratedRegion {
+    GeneratedRegion {
+        name: DEFAULT_REGION.to_string(),
+        line,
+        range: 0..0,
+    }
+}
+
+fn unclosed(name: &str) -> DirectiveError {
+    DirectiveError::UnclosedRegion(name.to_string())
+}
+
+#[rstest]
+#[case::no_markers("// bspg:\n// A\n", vec![])]
+#[case::region(
+    "a\n// bspg:begin generated\nb\n// bspg:end generated\nc",
+    vec![("generated", "b\n")]
+)]
+#[case::empty_region("// bspg:begin generated\n// bspg:end generated\n", vec![("generated", "")])]
+#[case::spaced(
+    "//  bspg:begin  generated \nb\n  // bspg:end \"generated\"\n",
+    vec![("generated", "b\n")]
+)]
+#[case::named(
+    "// bspg:begin ui\nb\n// bspg:end ui\n// bspg:begin game\nc\n// bspg:end game\n",
+    vec![("ui", "b\n"), ("game", "c\n")]
+)]
+fn test_find_generated_regions(#[case] source: &str, #[case] expected: Vec<(&str, &str)>) {
+    let regions = find_generated_regions(source).unwrap();
+    let regions = regions
+        .into_iter()
+        .map(|region| (region.name, &source[region.range]))
+        .collect_vec();
+    let expected = expected
+        .into_iter()
+        .map(|(name, contents)| (name.to_string(), contents))
+        .collect_vec();
+    assert_that!(regions).is_equal_to(expected);
+}
+
+#[rstest]
+#[case::unclosed("a\n// bspg:begin generated\nb\n", 2, unclosed("generated"))]
+#[case::nested(
+    "// bspg:begin generated\n// bspg:begin generated\n// bspg:end generated\n",
+    1,
+    unclosed("generated")
+)]
+#[case::crossed("// bspg:begin ui\n// bspg:end game\n", 1, unclosed("ui"))]
+#[case::unopened(
+    "a\n\n// bspg:end generated\n",
+    3,
+    DirectiveError::UnopenedRegion("generated".to_string())
+)]
+#[case::duplicate(
+    "// bspg:begin ui\n// bspg:end ui\n// bspg:begin ui\n",
+    3,
+    DirectiveError::DuplicateRegion("ui".to_string())
+)]
+fn test_find_generated_regions_unbalanced(
+    #[case] source: &str,
+    #[case] expected_line: usize,
+    #[case] expected_error: DirectiveError,
+) {
+    let error = find_generated_regions(source).unwrap_err();
+    let GeneratorError::Directive { span, error, .. } = error else {
+        panic!("expected a directive error, got {error:?}");
+    };
+    assert_that!(span.line).is_equal_to(expected_line);
+    assert_that!(*error).is_equal_to(expected_error);
+}
+
+const TAGGED_BLOCKS: &str = "\
+// bspg:naming_scheme short
+// bspg:plugin_name UiStatesPlugin
+// bspg:states_module_name ui_states
+// bspg: ui
+// Hud
+// Menu { Main Options }
+
+// bspg:begin ui
+// bspg: not_a_block
+// bspg:end ui
+
+// Gameplay states
+// bspg:plugin_name GameplayStatesPlugin
+// bspg:nonsense 1
+// bspg: gameplay
+// Playing
+// Paused
+fn main() {}
+";
+
+#[rstest]
+fn test_parse_template_blocks() {
+    let regions = find_generated_regions(TAGGED_BLOCKS).unwrap();
+    assert_that!(has_tagged_blocks(TAGGED_BLOCKS, &regions)).is_true();
+    let config = PluginConfig::default();
+    let blocks = parse_template_blocks(TAGGED_BLOCKS, &config, &regions).unwrap();
+    let [ui, gameplay] = blocks.as_slice() else {
+        panic!("expected two blocks, got {blocks:?}");
+    };
+
+    assert_that!(ui.tag.as_str()).is_equal_to("ui");
+    assert_that!(ui.line).is_equal_to(4);
+    assert_that!(ui.template).is_equal_to(vec!["Hud", "Menu { Main Options }"]);
+    assert_that!(ui.config.naming_scheme).is_equal_to(NamingScheme::Short);
+    assert_that!(ui.config.states_module_name.to_string()).is_equal_to("ui_states".to_string());
+    assert_that!(TAGGED_BLOCKS[..ui.end].to_string()).ends_with("// Menu { Main Options }\n");
+
+    assert_that!(gameplay.tag.as_str()).is_equal_to("gameplay");
+    assert_that!(gameplay.template).is_equal_to(vec!["Playing", "Paused"]);
+    // directives only apply to the block below them
+    assert_that!(gameplay.config.naming_scheme).is_equal_to(NamingScheme::Full);
+    assert_that!(gameplay.config.plugin_name.to_string())
+        .is_equal_to("GameplayStatesPlugin".to_string());
+    assert_that!(gameplay.warnings).has_length(1);
+    assert_that!(gameplay.warnings[0].to_string())
+        .is_equal_to("line 14: unknown setting: 'nonsense'".to_string());
+}
+
+#[rstest]
+#[case::untagged("// bspg:\n// A\n", false)]
+#[case::tagged("// A\n// bspg: ui\n// A\n", true)]
+#[case::inside_region("// bspg:begin generated\n// bspg: ui\n// bspg:end generated\n", false)]
+fn test_has_tagged_blocks(#[case] source: &str, #[case] expected: bool) {
+    let regions = find_generated_regions(source).unwrap();
+    assert_that!(has_tagged_blocks(source, &regions)).is_equal_to(expected);
+}
+
+#[rstest]
+fn test_parse_template_blocks_duplicate_tag() {
+    let template = "// bspg: ui\n// A\n\n// bspg: ui\n// B\n";
+    let config = PluginConfig::default();
+    let error = parse_template_blocks(template, &config, &[]).unwrap_err();
+    let GeneratorError::Directive { span, error, .. } = error else {
+        panic!("expected a directive error, got {error:?}");
+    };
+    assert_that!(span.line).is_equal_to(4);
+    assert_that!(*error).is_equal_to(DirectiveError::DuplicateBlock("ui".to_string()));
+}
+
+#[rstest]
+fn test_parse_template_blocks_strict() {
+    let template = "// bspg:strict true\n// bspg:nonsense 1\n// bspg: ui\n// A\n";
+    let config = PluginConfig::default();
+    let error = parse_template_blocks(template, &config, &[]).unwrap_err();
+    assert_that!(error.to_string())
+        .is_equal_to("Directive Error on line 2: unknown setting: 'nonsense'".to_string());
+}
+
+#[rstest]
+fn test_parse_template_header_with_region_keeps_comments() {
+    let template = "// bspg:strict true\n// my notes\n// bspg:\n// A\n// bspg:begin generated\n";
+    let mut config = PluginConfig::default();
+    let header = parse_template_header(template, &mut config, Some(&region(5))).unwrap();
+    assert_that!(header.comments_block).contains("// my notes");
+    assert_that!(header.template).is_equal_to(vec!["A"]);
+}
+
+#[rstest]
+#[case::code_before(
+    "use std::fmt;\n\n// bspg:nonsense 1\n// bspg:\n// A B\n// bspg:begin generated\n"
+)]
+#[case::code_between("// bspg:\n// A B\n\nfn hand_written() {}\n\n// bspg:begin generated\n")]
+#[case::blocks_before(
+    "// bspg:\n// C\n\n// bspg:nonsense 1\n// bspg:\n// A B\n// bspg:begin generated\n"
+)]
+fn test_parse_template_header_above_region(#[case] template: &str) {
+    let line = template.lines().count();
+    let mut config = PluginConfig::default();
+    let header = parse_template_header(template, &mut config, Some(&region(line))).unwrap();
+    assert_that!(header.template).is_equal_to(vec!["A B"]);
+    for warning in &header.warnings {
+        let directive = template.lines().nth(warning.line.unwrap() - 1).unwrap();
+        assert_that!(directive).is_equal_to("// bspg:nonsense 1");
+    }
+}
+
+#[rstest]
+fn test_parse_template_header_without_block_above_region() {
+    let template = "use std::fmt;\n// bspg:begin generated\n// bspg:end generated\n";
+    let mut config = PluginConfig::default();
+    let error = parse_template_header(template, &mut config, Some(&region(2))).unwrap_err();
+    let GeneratorError::Directive { span, error, .. } = error else {
+        panic!("expected a directive error, got {error:?}");
+    };
+    assert_that!(span.line).is_equal_to(2);
+    assert_that!(*error).is_equal_to(DirectiveError::MissingBlock("generated".to_string()));
+}
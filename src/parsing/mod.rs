@@ -1,10 +1,14 @@
+pub(crate) mod directive;
 pub(crate) mod header;
 mod parsers;
+pub(crate) mod printer;
+pub(crate) mod span;
 #[cfg(test)]
 mod tests;
 mod tokens;
+pub(crate) mod tree;
 
 pub use parsers::*;
-pub(crate) use tokens::*;
+pub use tokens::*;
 
 // TODO: investigate "tree-less parsing", see https://www.youtube.com/watch?v=NxiKlnUtyio
@@ -1,7 +1,7 @@
 use nom::branch::alt;
 use nom::bytes::complete::*;
 use nom::character::complete::*;
-use nom::combinator::recognize;
+use nom::combinator::{cut, recognize};
 use nom::multi::many0;
 use nom::sequence::*;
 use nom::{IResult, Parser};
@@ -83,14 +83,14 @@ pub fn parse_singleton(input: &'_ str) -> IResult<&'_ str, Node<'_>> {
 
 pub fn parse_enum(input: &str) -> IResult<&str, Node<'_>> {
     let (input, name) = terminated(skip(identifier), skip(open_enum)).parse(input)?;
-    let (input, children) = skip(parse_elements_until(close_enum)).parse(input)?;
+    // once the brace is open, report failures from inside instead of backtracking
+    let (input, children) = cut(skip(parse_elements_until(close_enum))).parse(input)?;
     Ok((input, Node::Enum(name, children)))
 }
 
 pub fn parse_list(input: &'_ str) -> IResult<&'_ str, Node<'_>> {
-    let (input, name) = skip(identifier).parse(input)?;
-    let (input, children) =
-        skip(preceded(open_list, parse_elements_until(close_list))).parse(input)?;
+    let (input, name) = terminated(skip(identifier), skip(open_list)).parse(input)?;
+    let (input, children) = cut(skip(parse_elements_until(close_list))).parse(input)?;
     Ok((input, Node::List(name, children)))
 }
 
@@ -0,0 +1,44 @@
+use std::fmt::Write;
+
+use crate::parsing::Node;
+
+const INDENT: &str = "    ";
+
+/// Print nodes back as a state definition, in the canonical layout:
+/// one node per line, children indented by four spaces.
+pub(crate) fn print_nodes(nodes: &[Node<'_>]) -> String {
+    let mut output = String::new();
+    for node in nodes {
+        print_node(node, 0, &mut output);
+    }
+    output
+}
+
+fn print_node(node: &Node<'_>, depth: usize, output: &mut String) {
+    let indent = INDENT.repeat(depth);
+    let (name, children, (open, close)) = match node {
+        Node::Singleton(name) => {
+            writeln!(output, "{indent}{name}").unwrap();
+            return;
+        }
+        Node::Comment(comment) if comment.is_empty() => {
+            writeln!(output, "{indent}//").unwrap();
+            return;
+        }
+        Node::Comment(comment) => {
+            writeln!(output, "{indent}// {}", **comment).unwrap();
+            return;
+        }
+        Node::Enum(name, children) => (name, children, ("{", "}")),
+        Node::List(name, children) => (name, children, ("[", "]")),
+    };
+    if children.is_empty() {
+        writeln!(output, "{indent}{name} {open}{close}").unwrap();
+        return;
+    }
+    writeln!(output, "{indent}{name} {open}").unwrap();
+    for child in children {
+        print_node(child, depth + 1, output);
+    }
+    writeln!(output, "{indent}{close}").unwrap();
+}
@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests;
+
+use std::ops::Range;
+
+use nom::Offset;
+
+/// A range of bytes in a source, along with the line and column it starts at
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub struct Span {
+    /// The byte it starts at
+    pub offset: usize,
+    /// Its length in bytes
+    pub len: usize,
+    /// The line it starts on, starting at 1
+    pub line: usize,
+    /// The character it starts at within its line, starting at 1
+    pub column: usize,
+}
+
+impl Span {
+    /// The span of `len` bytes at `offset` in `source`
+    pub(crate) fn new(source: &str, offset: usize, len: usize) -> Self {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+        Self {
+            offset,
+            len,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+
+    /// The span of the text of `line` in `source`, starting at 1, or the end of
+    /// `source` if it has fewer lines
+    pub(crate) fn of_line(source: &str, line: usize) -> Self {
+        let offset = source
+            .split_inclusive('\n')
+            .take(line.saturating_sub(1))
+            .map(str::len)
+            .sum::<usize>();
+        let text = source[offset..].lines().next().unwrap_or_default();
+        Self::new(source, offset, text.trim_end().len())
+    }
+
+    /// The bytes it covers
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.len
+    }
+}
+
+/// Where the states that are parsed came from in the file they were read from,
+/// which also has the directives, or comments around them in a template
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct SourceMap<'a> {
+    source: &'a str,
+    /// The offset of every line in the states, and of the same line in the source
+    lines: Vec<(usize, usize)>,
+}
+
+impl<'a> SourceMap<'a> {
+    /// The states are `lines`, slices of `source`, joined by a separator of
+    /// `separator_len` bytes
+    pub(crate) fn from_lines(source: &'a str, lines: &[&str], separator_len: usize) -> Self {
+        let mut offset = 0;
+        let lines = lines
+            .iter()
+            .map(|line| {
+                let entry = (offset, source.offset(line));
+                offset += line.len() + separator_len;
+                entry
+            })
+            .collect();
+        Self { source, lines }
+    }
+
+    /// The span in the source of `len` bytes at `offset` in the states
+    pub(crate) fn span(&self, offset: usize, len: usize) -> Span {
+        let (states_offset, source_offset) = self
+            .lines
+            .iter()
+            .take_while(|(start, _)| *start <= offset)
+            .last()
+            .copied()
+            .unwrap_or_default();
+        let offset = (source_offset + offset - states_offset).min(self.source.len());
+        Span::new(self.source, offset, len.min(self.source.len() - offset))
+    }
+}
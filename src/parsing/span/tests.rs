@@ -0,0 +1,57 @@
+use rstest::rstest;
+use speculoos::prelude::*;
+
+use crate::parsing::span::{SourceMap, Span};
+
+#[rstest]
+#[case::start("A\nB { C }", 0, (1, 1))]
+#[case::second_line("A\nB { C }", 6, (2, 5))]
+#[case::end("A\nB { C }", 9, (2, 8))]
+#[case::characters("Ä B", 3, (1, 3))]
+fn test_span_line_and_column(
+    #[case] source: &str,
+    #[case] offset: usize,
+    #[case] expected: (usize, usize),
+) {
+    let span = Span::new(source, offset, 0);
+    assert_that!((span.line, span.column)).is_equal_to(expected);
+}
+
+#[rstest]
+#[case::first("// bspg:strict\r\nA\n", 1, "// bspg:strict")]
+#[case::last("A\n// bspg:strict  ", 2, "// bspg:strict")]
+#[case::past_the_end("A\n", 3, "")]
+fn test_span_of_line(#[case] source: &str, #[case] line: usize, #[case] expected: &str) {
+    let span = Span::of_line(source, line);
+    assert_that!(source.get(span.range())).is_equal_to(Some(expected));
+}
+
+#[rstest]
+fn test_source_map_skips_directives() {
+    let source = "// bspg:plugin_name MyPlugin\nA\n// bspg:strict true\nB { C }\n";
+    let lines = source
+        .split_inclusive('\n')
+        .filter(|line| !line.starts_with("// bspg:"))
+        .collect::<Vec<_>>();
+    let states = lines.concat();
+    let offset = states.find('C').unwrap();
+    let span = SourceMap::from_lines(source, &lines, 0).span(offset, 1);
+    assert_that!(source.get(span.range())).is_equal_to(Some("C"));
+    assert_that!((span.line, span.column)).is_equal_to((4, 5));
+}
+
+#[rstest]
+fn test_source_map_of_template_lines() {
+    let source = "// bspg:\n//   A\n//   B { C }\nfn main() {}\n";
+    let lines = source
+        .lines()
+        .skip(1)
+        .take(2)
+        .map(|line| line.strip_prefix("//").unwrap().trim())
+        .collect::<Vec<_>>();
+    let states = lines.join("\n");
+    let offset = states.find('C').unwrap();
+    let span = SourceMap::from_lines(source, &lines, 1).span(offset, 1);
+    assert_that!(source.get(span.range())).is_equal_to(Some("C"));
+    assert_that!((span.line, span.column)).is_equal_to((3, 10));
+}
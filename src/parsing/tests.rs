@@ -3,6 +3,7 @@ use nom::branch::alt;
 use speculoos::prelude::*;
 
 use crate::parsing::parsers::*;
+use crate::parsing::printer::print_nodes;
 use crate::parsing::*;
 use crate::testing::*;
 
@@ -137,12 +138,17 @@ fn test_parse_node_messy_example() {
 
 #[rstest]
 fn test_parse_list_incomplete() {
-    assert_compact_debug_snapshot!(parse_list("Root [ A"), @r#"Err(Error(Error { input: "", code: Tag })) "#);
+    assert_compact_debug_snapshot!(parse_list("Root [ A"), @r#"Err(Failure(Error { input: "", code: Tag }))"#);
 }
 
 #[rstest]
 fn test_parse_enum_incomplete() {
-    assert_compact_debug_snapshot!(parse_enum("Root { A"), @r#"Err(Error(Error { input: "", code: Tag }))"#);
+    assert_compact_debug_snapshot!(parse_enum("Root { A"), @r#"Err(Failure(Error { input: "", code: Tag }))"#);
+}
+
+#[rstest]
+fn test_parse_enum_invalid_variant() {
+    assert_compact_debug_snapshot!(parse_enum("Root { A b }"), @r#"Err(Failure(Error { input: "b }", code: Tag }))"#);
 }
 
 #[rstest]
@@ -220,3 +226,36 @@ fn test_parse_state_text(#[case] source: &str, #[case] root_count: usize) {
     let (_, parse_nodes) = parse_config(source).unwrap();
     assert_that!(parse_nodes).has_length(root_count);
 }
+
+#[rstest]
+fn test_print_nodes() {
+    let input = "// intro\nA, B{C [D,E] F{}} G[]//\nH";
+    let (_, nodes) = parse_config(input).unwrap();
+    assert_snapshot!(print_nodes(&nodes), @r"
+    // intro
+    A
+    B {
+        C [
+            D
+            E
+        ]
+        F {}
+    }
+    G []
+    //
+    H
+    ");
+}
+
+#[rstest]
+#[case("A B C")]
+#[case("Root { A { B } C }")]
+#[case("Root [ A { B, C [D E {F G}] H } I J ]")]
+#[case("A {\n // B\n C\n}")]
+fn test_print_nodes_round_trip(#[case] input: &str) {
+    let (_, nodes) = parse_config(input).unwrap();
+    let printed = print_nodes(&nodes);
+    let (rest, reparsed) = parse_config(&printed).unwrap();
+    assert_that!(rest.trim()).is_empty();
+    assert_that!(reparsed).is_equal_to(nodes);
+}
@@ -1,5 +1,6 @@
 use derive_more::{Deref, From};
-use nom::error::ErrorKind;
+
+use crate::processing::ProcessingError;
 
 pub type NomError<'a, I = &'a str> = nom::error::Error<I>;
 pub type NomErr<'a, E = NomError<'a>> = nom::Err<E>;
@@ -13,8 +14,9 @@ pub enum Token {
     CloseList,
 }
 
+/// The name of a state
 #[derive(Clone, Copy, Debug, Deref, From, PartialEq)]
-pub struct Identifier<'a>(&'a str);
+pub(crate) struct Identifier<'a>(&'a str);
 
 impl std::fmt::Display for Identifier<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -22,30 +24,40 @@ impl std::fmt::Display for Identifier<'_> {
     }
 }
 
+/// A `//` comment, without the slashes
 #[derive(Clone, Copy, Debug, Deref, From, PartialEq)]
-pub struct Comment<'a>(&'a str);
+pub(crate) struct Comment<'a>(&'a str);
 
+/// A state in a parsed state definition, borrowed from the source; the public
+/// form is [`StateNode`](crate::prelude::StateNode)
 #[derive(Clone, Debug, PartialEq)]
-pub enum Node<'a> {
+pub(crate) enum Node<'a> {
+    /// A state without children, e.g. `Loading`
     Singleton(Identifier<'a>),
+    /// A state with one of its children active, e.g. `Ready { Menu Game }`
     Enum(Identifier<'a>, Vec<Node<'a>>),
+    /// A state with all of its children active, e.g. `Game [ Hud Map ]`
     List(Identifier<'a>, Vec<Node<'a>>),
+    /// A comment between the states
     Comment(Comment<'a>),
 }
 
 impl<'a> Node<'a> {
+    /// A state without children
     pub fn singleton<I: Into<Identifier<'a>>>(name: I) -> Self {
         Self::Singleton(name.into())
     }
-    fn identifier(&self) -> Option<&Identifier<'a>> {
+    pub(crate) fn identifier(&self) -> Option<&Identifier<'a>> {
         match self {
             Self::Singleton(id) | Self::Enum(id, _) | Self::List(id, _) => Some(id),
             _ => None,
         }
     }
+    /// The name of the state, `None` for comments
     pub fn name(&self) -> Option<&str> {
         self.identifier().map(|id| id.0)
     }
+    /// The child states, including comments
     pub fn children(&self) -> Vec<Node<'a>> {
         match self {
             Self::Singleton(_) | Self::Comment(_) => Vec::new(),
@@ -53,6 +65,7 @@ impl<'a> Node<'a> {
             Self::List(_, children) => children.clone(),
         }
     }
+    /// The number of nodes in this tree, including itself
     pub fn get_tree_size(&self) -> usize {
         match self {
             Self::Comment(_) => 1,
@@ -69,15 +82,16 @@ impl<'a> Node<'a> {
 }
 
 impl<'a> TryFrom<&'a str> for Node<'a> {
-    type Error = nom::Err<nom::error::Error<&'a str>>;
+    type Error = ProcessingError;
 
     fn try_from(s: &'a str) -> Result<Self, Self::Error> {
-        crate::parsing::parse_node(s).and_then(|(rest, node)| {
-            rest.trim()
-                .is_empty()
-                .then_some(node)
-                .ok_or_else(|| nom::Err::Failure(nom::error::Error::new(s, ErrorKind::Complete)))
-        })
+        let (rest, node) = crate::parsing::parse_node(s).map_err(ProcessingError::from_nom)?;
+        if rest.trim().is_empty() {
+            Ok(node)
+        } else {
+            Err(ProcessingError::parse(rest.trim_start()))
+        }
+        .map_err(|error| error.locate(s))
     }
 }
 
@@ -85,22 +99,22 @@ impl<'a> TryFrom<&'a str> for Node<'a> {
 #[cfg(test)]
 #[cfg_attr(coverage_nightly, coverage(off))]
 impl<'a> Node<'a> {
-    pub fn enumeration<I: Into<Identifier<'a>>, V: IntoIterator<Item = Node<'a>>>(
+    pub(crate) fn enumeration<I: Into<Identifier<'a>>, V: IntoIterator<Item = Node<'a>>>(
         name: I,
         variants: V,
     ) -> Self {
         Self::Enum(name.into(), variants.into_iter().collect())
     }
-    pub fn list_empty<I: Into<Identifier<'a>>>(name: I) -> Self {
+    pub(crate) fn list_empty<I: Into<Identifier<'a>>>(name: I) -> Self {
         Self::List(name.into(), vec![])
     }
-    pub fn list<I: Into<Identifier<'a>>, V: IntoIterator<Item = Node<'a>>>(
+    pub(crate) fn list<I: Into<Identifier<'a>>, V: IntoIterator<Item = Node<'a>>>(
         name: I,
         variants: V,
     ) -> Self {
         Self::List(name.into(), variants.into_iter().collect())
     }
-    pub fn comment<C: Into<Comment<'a>>>(name: C) -> Self {
+    pub(crate) fn comment<C: Into<Comment<'a>>>(name: C) -> Self {
         Self::Comment(name.into())
     }
 }
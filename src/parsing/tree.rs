@@ -0,0 +1,54 @@
+//! The public, owned form of a parsed state definition
+
+use crate::parsing::Node;
+
+/// A state in a parsed state definition, see [`parse_states`](crate::prelude::parse_states)
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum StateNode {
+    /// A state without children, e.g. `Loading`
+    Singleton(String),
+    /// A state with one of its children active, e.g. `Ready { Menu Game }`
+    Enum(String, Vec<StateNode>),
+    /// A state with all of its children active, e.g. `Game [ Hud Map ]`
+    List(String, Vec<StateNode>),
+    /// A comment between the states, without the slashes
+    Comment(String),
+}
+
+impl StateNode {
+    /// The name of the state, `None` for comments
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::Singleton(name) | Self::Enum(name, _) | Self::List(name, _) => Some(name),
+            Self::Comment(_) => None,
+        }
+    }
+    /// The child states, including comments
+    pub fn children(&self) -> &[StateNode] {
+        match self {
+            Self::Singleton(_) | Self::Comment(_) => &[],
+            Self::Enum(_, children) | Self::List(_, children) => children,
+        }
+    }
+    /// The number of nodes in this tree, including itself
+    pub fn get_tree_size(&self) -> usize {
+        1 + self
+            .children()
+            .iter()
+            .map(StateNode::get_tree_size)
+            .sum::<usize>()
+    }
+}
+
+impl From<&Node<'_>> for StateNode {
+    fn from(node: &Node<'_>) -> Self {
+        let children = |children: &[Node<'_>]| children.iter().map(Self::from).collect();
+        match node {
+            Node::Singleton(name) => Self::Singleton(name.to_string()),
+            Node::Enum(name, nodes) => Self::Enum(name.to_string(), children(nodes)),
+            Node::List(name, nodes) => Self::List(name.to_string(), children(nodes)),
+            Node::Comment(comment) => Self::Comment(comment.to_string()),
+        }
+    }
+}
@@ -4,14 +4,17 @@ mod tests;
 use std::borrow::Cow;
 use std::collections::VecDeque;
 
-use bevy_platform::collections::HashSet;
+use bevy_platform::collections::HashMap;
 use bevy_utils::default;
-use indoc::formatdoc;
-use itertools::{Itertools, concat};
+use itertools::Itertools;
+use nom::Offset;
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
 
 use crate::config::{NamingScheme, PluginConfig, PluginName};
-use crate::generate::core::REQUIRED_DERIVES;
-use crate::parsing::{Node, NomErr};
+use crate::generate::core::{REQUIRED_DERIVES, format_tokens};
+use crate::parsing::span::{SourceMap, Span};
+use crate::parsing::{Identifier, Node, NomErr, parse_config};
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub enum NodeType {
@@ -92,7 +95,7 @@ pub fn apply_naming_scheme(
     naming_scheme: NamingScheme,
     nodes: &mut [NodeData],
 ) -> Result<(), ProcessingError> {
-    let mut names = HashSet::new();
+    let mut names = HashMap::new();
     let mut resolved_names = Vec::new();
     for node in nodes.iter() {
         let base_name = &node.name;
@@ -116,12 +119,17 @@ pub fn apply_naming_scheme(
             }
             NamingScheme::None => base_name.clone(),
         };
-        if !names.insert(resolved_name.clone()) {
-            return Err(ProcessingError::DuplicateName {
+        if let Some(&first) = names.get(&resolved_name) {
+            return Err(ProcessingError::DuplicateName(Box::new(DuplicateName {
                 resolved_name,
                 original_name: base_name.clone(),
-            });
+                node_path: node_path(nodes, node.index),
+                span: None,
+                first_node_path: node_path(nodes, first),
+                first_span: None,
+            })));
         }
+        names.insert(resolved_name.clone(), node.index);
 
         resolved_names.push(resolved_name);
     }
@@ -132,183 +140,455 @@ pub fn apply_naming_scheme(
     Ok(())
 }
 
-#[derive(Debug, thiserror::Error)]
+/// The names of the node and its ancestors, from the root down: `Game.Ready.Menu`
+fn node_path(nodes: &[NodeData], index: usize) -> String {
+    let mut names = vec![nodes[index].name.as_str()];
+    let mut current = &nodes[index];
+    while let Some(parent) = current.parent {
+        current = &nodes[parent];
+        names.push(&current.name);
+    }
+    names.into_iter().rev().join(".")
+}
+
+/// The identifiers of the states at `path` among `nodes`, see [`node_path`]
+fn find_identifiers<'a>(nodes: &[Node<'a>], path: &[&str]) -> Vec<Identifier<'a>> {
+    let Some((name, rest)) = path.split_first() else {
+        return Vec::new();
+    };
+    nodes
+        .iter()
+        .filter(|node| node.name() == Some(name))
+        .flat_map(|node| match rest {
+            [] => node.identifier().copied().into_iter().collect(),
+            rest => find_identifiers(&node.children(), rest),
+        })
+        .collect()
+}
+
+/// The offset of the innermost `{` or `[` in `states` that is never closed
+fn unclosed_delimiter(states: &str) -> Option<usize> {
+    let mut open = Vec::new();
+    for line in states.lines() {
+        // delimiters in comments do not count
+        let code = line.find("//").map_or(line, |comment| &line[..comment]);
+        for (index, char) in code.char_indices() {
+            match char {
+                '{' | '[' => open.push(states.offset(line) + index),
+                '}' | ']' => {
+                    open.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+    open.pop()
+}
+
+/// What is wrong with a state definition, or with the code generated from it
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
+#[non_exhaustive]
 pub enum ProcessingError {
-    #[error("Duplicate name: resolved_name='{resolved_name}' original_name='{original_name}'")]
-    DuplicateName {
-        resolved_name: String,
-        original_name: String,
+    /// The state definition cannot be parsed
+    #[error("Failed to parse {}", parse_position(&.0.unparsed))]
+    Parse(Box<ParseFailure>),
+    /// Two states end up with the same name under the [`NamingScheme`]
+    #[error(
+        "Duplicate name: resolved_name='{}' original_name='{}' (at {}, first at {})",
+        .0.resolved_name,
+        .0.original_name,
+        .0.node_path,
+        .0.first_node_path
+    )]
+    DuplicateName(Box<DuplicateName>),
+    /// A state name, or the name it resolves to, is not a valid Rust identifier
+    #[error("Invalid name: '{name}' is not a valid Rust identifier (at {node_path})")]
+    InvalidName {
+        /// The name as it would be generated
+        name: String,
+        /// The state and its ancestors, e.g. `Game.Ready.Menu`
+        node_path: String,
+        /// Where the state is defined
+        span: Option<Span>,
+    },
+    /// A name or path in the [`PluginConfig`] is not valid Rust
+    #[error("Invalid configuration: `{field}` = '{value}' ({reason})")]
+    InvalidConfig {
+        /// The field of the [`PluginConfig`]
+        field: &'static str,
+        /// Its value
+        value: String,
+        /// Why it was rejected
+        reason: String,
+    },
+    /// The generated code does not parse as Rust
+    #[error("Generated invalid Rust: {message}")]
+    InvalidRust {
+        /// What the Rust parser reported
+        message: String,
     },
-    #[error("Unspecified error: {0}")]
-    Custom(String),
-    #[error("Failed to parse! Final state: {0:?}")]
-    Parsing(#[from] nom::Err<nom::error::Error<String>>),
-    #[error("Invalid configuration: {0}")]
-    InvalidConfig(String),
 }
 
-impl<'a> From<NomErr<'a>> for ProcessingError {
-    fn from(value: NomErr<'a>) -> Self {
-        Self::from(value.to_owned())
+/// The states that would get the same name, see [`ProcessingError::DuplicateName`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct DuplicateName {
+    /// The name both states would get
+    pub resolved_name: String,
+    /// The name of the second state, as written
+    pub original_name: String,
+    /// The second state and its ancestors, e.g. `Game.Ready.Menu`
+    pub node_path: String,
+    /// Where the second state is defined
+    pub span: Option<Span>,
+    /// The first state with the name and its ancestors
+    pub first_node_path: String,
+    /// Where the first state is defined
+    pub first_span: Option<Span>,
+}
+
+/// Where parsing a state definition stopped, see [`ProcessingError::Parse`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct ParseFailure {
+    /// The rest of the state definition, from where parsing stopped
+    pub unparsed: String,
+    /// Where parsing stopped
+    pub span: Option<Span>,
+    /// The `{` or `[` that is never closed, if the input ended inside of it
+    pub unclosed: Option<Span>,
+}
+
+fn parse_position(unparsed: &str) -> String {
+    if unparsed.is_empty() {
+        "the end of the input, is something missing?".to_string()
+    } else {
+        format!("input starting at: {unparsed:?}")
     }
 }
 
-pub(crate) fn build_plugin_source(
+impl ProcessingError {
+    /// The remainder of the input where parsing stopped, if parsing failed
+    pub fn unparsed_input(&self) -> Option<&str> {
+        match self {
+            Self::Parse(failure) => Some(&failure.unparsed),
+            Self::DuplicateName(_)
+            | Self::InvalidName { .. }
+            | Self::InvalidConfig { .. }
+            | Self::InvalidRust { .. } => None,
+        }
+    }
+
+    /// Where the problem is in the source, if it is about a part of it
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Self::Parse(failure) => failure.span,
+            Self::DuplicateName(duplicate) => duplicate.span,
+            Self::InvalidName { span, .. } => *span,
+            Self::InvalidConfig { .. } | Self::InvalidRust { .. } => None,
+        }
+    }
+
+    /// The state the problem is about, e.g. `Game.Ready.Menu`
+    pub fn node_path(&self) -> Option<&str> {
+        match self {
+            Self::DuplicateName(duplicate) => Some(&duplicate.node_path),
+            Self::InvalidName { node_path, .. } => Some(node_path),
+            Self::Parse(_) | Self::InvalidConfig { .. } | Self::InvalidRust { .. } => None,
+        }
+    }
+
+    /// A stable, machine-readable name for the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Parse(_) => "bspg::parse",
+            Self::DuplicateName(_) => "bspg::duplicate_name",
+            Self::InvalidName { .. } => "bspg::invalid_name",
+            Self::InvalidConfig { .. } => "bspg::invalid_config",
+            Self::InvalidRust { .. } => "bspg::invalid_rust",
+        }
+    }
+
+    /// Parsing stopped at `unparsed`
+    pub(crate) fn parse(unparsed: &str) -> Self {
+        Self::Parse(Box::new(ParseFailure {
+            unparsed: unparsed.to_string(),
+            span: None,
+            unclosed: None,
+        }))
+    }
+
+    pub(crate) fn from_nom(error: NomErr<'_>) -> Self {
+        match error {
+            nom::Err::Error(error) | nom::Err::Failure(error) => Self::parse(error.input),
+            nom::Err::Incomplete(_) => Self::parse(""),
+        }
+    }
+
+    pub(crate) fn from_syn(error: syn::Error) -> Self {
+        Self::InvalidRust {
+            message: error.to_string(),
+        }
+    }
+
+    /// Point the error at where it is in `states`, the state definition that
+    /// was parsed
+    pub(crate) fn locate(self, states: &str) -> Self {
+        match self {
+            Self::Parse(mut failure) => {
+                // at the end of the input, point right after the last token
+                let offset = if failure.unparsed.trim().is_empty() {
+                    states.trim_end().len()
+                } else {
+                    states.len().saturating_sub(failure.unparsed.len())
+                };
+                let len = failure
+                    .unparsed
+                    .lines()
+                    .next()
+                    .map_or(0, |line| line.trim_end().len());
+                failure.span = Some(Span::new(states, offset, len));
+                if failure.unparsed.trim().is_empty() {
+                    failure.unclosed =
+                        unclosed_delimiter(states).map(|offset| Span::new(states, offset, 1));
+                }
+                Self::Parse(failure)
+            }
+            Self::DuplicateName(mut duplicate) => {
+                // states with the same path are told apart by their order
+                let occurrence = usize::from(duplicate.node_path == duplicate.first_node_path);
+                duplicate.span = span_of(states, &duplicate.node_path, occurrence);
+                duplicate.first_span = span_of(states, &duplicate.first_node_path, 0);
+                Self::DuplicateName(duplicate)
+            }
+            Self::InvalidName {
+                name, node_path, ..
+            } => Self::InvalidName {
+                span: span_of(states, &node_path, 0),
+                name,
+                node_path,
+            },
+            error @ (Self::InvalidConfig { .. } | Self::InvalidRust { .. }) => error,
+        }
+    }
+
+    /// Move the spans from the states that were parsed to the file they are in
+    pub(crate) fn relocate(self, source_map: &SourceMap<'_>) -> Self {
+        let relocate = |span: Option<Span>| span.map(|span| source_map.span(span.offset, span.len));
+        match self {
+            Self::Parse(mut failure) => {
+                failure.span = relocate(failure.span);
+                failure.unclosed = relocate(failure.unclosed);
+                Self::Parse(failure)
+            }
+            Self::DuplicateName(mut duplicate) => {
+                duplicate.span = relocate(duplicate.span);
+                duplicate.first_span = relocate(duplicate.first_span);
+                Self::DuplicateName(duplicate)
+            }
+            Self::InvalidName {
+                name,
+                node_path,
+                span,
+            } => Self::InvalidName {
+                name,
+                node_path,
+                span: relocate(span),
+            },
+            error @ (Self::InvalidConfig { .. } | Self::InvalidRust { .. }) => error,
+        }
+    }
+}
+
+/// Where the state at `node_path` is defined in `states`, the `occurrence`th
+/// one if there are several with the same path
+fn span_of(states: &str, node_path: &str, occurrence: usize) -> Option<Span> {
+    let nodes = parse_config(states)
+        .map(|(_, nodes)| nodes)
+        .unwrap_or_default();
+    let path = node_path.split('.').collect_vec();
+    // the first part of the path may be the implicit root state
+    let identifiers = match find_identifiers(&nodes, &path) {
+        identifiers if identifiers.is_empty() => find_identifiers(&nodes, &path[1..]),
+        identifiers => identifiers,
+    };
+    identifiers
+        .get(occurrence)
+        .map(|identifier| Span::new(states, states.offset(identifier), identifier.len()))
+}
+
+/// The identifier for a state name, which the parser accepts more freely than Rust
+fn state_ident(name: &str, nodes: &[NodeData], index: usize) -> Result<Ident, ProcessingError> {
+    syn::parse_str(name).map_err(|_| ProcessingError::InvalidName {
+        name: name.to_string(),
+        node_path: node_path(nodes, index),
+        span: None,
+    })
+}
+
+/// Parse a configured name or path, reporting which field was rejected
+pub(crate) fn parse_config_value<T: syn::parse::Parse>(
+    field: &'static str,
+    value: &str,
+) -> Result<T, ProcessingError> {
+    syn::parse_str(value).map_err(|error| ProcessingError::InvalidConfig {
+        field,
+        value: value.to_string(),
+        reason: error.to_string(),
+    })
+}
+
+pub(crate) fn build_plugin_tokens(
     nodes: Vec<NodeData>,
     config: PluginConfig,
-) -> Result<String, ProcessingError> {
+) -> Result<TokenStream, ProcessingError> {
+    config.validate_fields()?;
     let PluginConfig {
         additional_derives: derives,
         plugin_name,
-        root_state_name,
+        root_state_name: _,
         naming_scheme: _,
         states_module_name,
+        strict: _,
+        force: _,
+        header_style: _,
+        rustfmt: _,
     } = config;
 
-    let derives = concat([
-        REQUIRED_DERIVES
-            .iter()
-            .copied()
-            .map(Cow::Borrowed)
-            .collect_vec(),
-        derives.into_iter().map(Cow::from).collect_vec(),
-    ])
-    .into_iter()
-    .unique()
-    .join(", ");
-    let definitions_source = nodes
+    let derives: Vec<syn::Path> = REQUIRED_DERIVES
         .iter()
-        .flat_map(|node| {
-            let derives = node
-                .parent
-                .map(|parent_id| {
-                    let parent = &nodes[parent_id];
-                    let parent_name = parent.resolved_name.as_ref().unwrap();
-
-                    match parent.node_type {
-                        NodeType::Enum => formatdoc! {"
-                            #[derive(bevy::prelude::SubStates, {derives})]
-                            #[source({parent_name} = {parent_name}::{variant})]
-                            ", variant = node.name
-                        },
-                        _ => formatdoc! {"
-                            #[derive(bevy::prelude::SubStates, {derives})]
-                            #[source({parent_name} = {parent_name})]
-                        "},
+        .copied()
+        .map(Cow::Borrowed)
+        .chain(derives)
+        .unique()
+        .map(|derive| parse_config_value("additional_derives", &derive))
+        .try_collect()?;
+    let states_module_name: Ident = parse_config_value("states_module_name", &states_module_name)?;
+    let resolved_names: Vec<Ident> = nodes
+        .iter()
+        .map(|node| {
+            let resolved_name = node
+                .resolved_name
+                .as_ref()
+                .expect("Node name has not been resolved!");
+            state_ident(resolved_name, &nodes, node.index)
+        })
+        .try_collect()?;
+
+    let definitions: Vec<TokenStream> = nodes
+        .iter()
+        .zip(&resolved_names)
+        .map(|(node, resolved_name)| {
+            let derive_attributes = if let Some(parent_id) = node.parent {
+                let parent = &nodes[parent_id];
+                let parent_name = &resolved_names[parent_id];
+                let source = match parent.node_type {
+                    NodeType::Enum => {
+                        let variant = state_ident(&node.name, &nodes, node.index)?;
+                        quote!(#parent_name = #parent_name::#variant)
                     }
-                })
-                .unwrap_or_else(|| formatdoc! {"#[derive(bevy::prelude::States, {derives})]"})
-                .trim()
-                .to_string();
-            let resolved_name = node.resolved_name.as_ref().unwrap();
-
-            let source_for_singleton = || {
-                formatdoc! {"
-                {derives}
-                pub struct {resolved_name};
-            "}
-            };
-            let source_for_enum = |variants: &[&str]| {
-                formatdoc! {"
-                {derives}
-                pub enum {resolved_name} {{
-                    #[default]
-                    {variants}
-                }}
-                ", variants = variants.join(",\n      ")
+                    _ => quote!(#parent_name = #parent_name),
+                };
+                quote! {
+                    #[derive(bevy::prelude::SubStates, #(#derives),*)]
+                    #[source(#source)]
                 }
+            } else {
+                quote!(#[derive(bevy::prelude::States, #(#derives),*)])
             };
 
-            // return the generated output preceded by its comments
-            let mut comments = node
-                .comments
-                .iter()
-                .map(|comment| format!("// {comment}"))
-                .collect_vec();
-            comments.push(match node.node_type {
-                NodeType::Singleton => source_for_singleton(),
-                NodeType::Enum => {
-                    if node.variants.is_empty() {
-                        source_for_singleton()
-                    } else {
-                        let variants = node.variants.iter().map(String::as_str).collect_vec();
-                        source_for_enum(&variants)
-                    }
-                }
-                NodeType::List => source_for_singleton(),
-            });
-            comments
-        })
-        .join("\n");
-
-    let plugin_builder = if let Some(root_state_name) = root_state_name.as_ref() {
-        let states_module_name: &str = states_module_name.as_ref();
-        let init_state = format!(".init_state::<{states_module_name}::{root_state_name}>()");
-        let sub_states = nodes
-            .iter()
-            .flat_map(|node| {
-                let resolved_name = node
-                    .resolved_name
-                    .as_ref()
-                    .expect("Node name has not been resolved!");
-                if node.parent.map(|p| nodes[p].clone()).is_some() {
-                    Some(format!(
-                        ".add_sub_state::<{states_module_name}::{resolved_name}>()"
-                    ))
+            // comments on a node are carried over as its documentation
+            let docs = node.comments.iter().map(|comment| {
+                if comment.is_empty() {
+                    String::new()
                 } else {
-                    None
+                    format!(" {comment}")
                 }
-            })
-            .join("\n            ");
-        format!("app{init_state}{sub_states};")
-    } else {
-        let states_module_name = states_module_name.as_ref();
-        let states = nodes
-            .iter()
-            .map(|node| {
-                let resolved_name = node
-                    .resolved_name
-                    .clone()
-                    .expect("Node name has not been resolved!");
-                if node.parent.map(|p| nodes[p].clone()).is_some() {
-                    format!(".add_sub_state::<{states_module_name}::{resolved_name}>()")
-                } else {
-                    format!(".init_state::<{states_module_name}::{resolved_name}>()")
+            });
+
+            // the variants are the names of the children, in order
+            let children = nodes
+                .iter()
+                .filter(|child| child.parent == Some(node.index));
+            let variants: Vec<Ident> = node
+                .variants
+                .iter()
+                .zip(children)
+                .map(|(variant, child)| state_ident(variant, &nodes, child.index))
+                .try_collect()?;
+            let definition = match (&node.node_type, variants.split_first()) {
+                (NodeType::Enum, Some((first, rest))) => {
+                    quote! {
+                        pub enum #resolved_name {
+                            #[default]
+                            #first,
+                            #(#rest),*
+                        }
+                    }
                 }
+                _ => quote!(pub struct #resolved_name;),
+            };
+
+            Ok(quote! {
+                #(#[doc = #docs])*
+                #derive_attributes
+                #definition
             })
-            .join("\n            ");
-        format!("app{states};")
-    };
+        })
+        .try_collect()?;
+
+    let states = nodes
+        .iter()
+        .zip(&resolved_names)
+        .map(|(node, resolved_name)| {
+            if node.parent.is_some() {
+                quote!(.add_sub_state::<#states_module_name::#resolved_name>())
+            } else {
+                quote!(.init_state::<#states_module_name::#resolved_name>())
+            }
+        });
+    let plugin_builder = quote!(app #(#states)*;);
 
     let plugin_def = match plugin_name {
         PluginName::Struct(plugin_name) => {
-            formatdoc! {"
-                pub struct {plugin_name};
-                impl bevy::app::Plugin for {plugin_name} {{
-                    fn build(&self, app: &mut bevy::app::App) {{
-                        {plugin_builder}
-                    }}
-                }}
-            "}
+            let plugin_name: Ident = parse_config_value("plugin_name", &plugin_name)?;
+            quote! {
+                pub struct #plugin_name;
+                impl bevy::app::Plugin for #plugin_name {
+                    fn build(&self, app: &mut bevy::app::App) {
+                        #plugin_builder
+                    }
+                }
+            }
         }
         PluginName::Function(plugin_name) => {
-            formatdoc! {"
-                pub fn {plugin_name}(app: &mut bevy::app::App) {{
-                    {plugin_builder}
-                }}
-            "}
+            let plugin_name: Ident = parse_config_value("plugin_name", &plugin_name)?;
+            quote! {
+                pub fn #plugin_name(app: &mut bevy::app::App) {
+                    #plugin_builder
+                }
+            }
         }
     };
 
-    Ok(formatdoc! {"
+    Ok(quote! {
         use bevy::prelude::AppExtStates;
         #[allow(missing_docs)]
-        pub mod {states_module_name} {{
+        pub mod #states_module_name {
             use bevy::prelude::StateSet;
-            {definitions_source}
-        }}
-        {plugin_def}
-    "})
+            #(#definitions)*
+        }
+        #plugin_def
+    })
+}
+
+#[cfg(test)]
+pub(crate) fn build_plugin_source(
+    nodes: Vec<NodeData>,
+    config: PluginConfig,
+) -> Result<String, ProcessingError> {
+    let tokens = build_plugin_tokens(nodes, config)?;
+    format_tokens(tokens).map_err(ProcessingError::from_syn)
 }
 
 pub(crate) fn remove_root_node(nodes: &mut Vec<NodeData>) {
@@ -345,11 +625,19 @@ pub(crate) fn process_nodes(
     Ok(nodes)
 }
 
-pub fn convert_nodes_into_plugin_source(
+pub fn convert_nodes_into_plugin_tokens(
     nodes: Vec<Node<'_>>,
     config: PluginConfig,
-) -> Result<String, ProcessingError> {
+) -> Result<TokenStream, ProcessingError> {
     let nodes = process_nodes(nodes, config.naming_scheme, config.root_state_name.clone())?;
     assert!(nodes.iter().all(|node| node.resolved_name.is_some()));
-    build_plugin_source(nodes, config)
+    build_plugin_tokens(nodes, config)
+}
+
+pub fn convert_nodes_into_plugin_source(
+    nodes: Vec<Node<'_>>,
+    config: PluginConfig,
+) -> Result<String, ProcessingError> {
+    let tokens = convert_nodes_into_plugin_tokens(nodes, config)?;
+    format_tokens(tokens).map_err(ProcessingError::from_syn)
 }
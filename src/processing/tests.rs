@@ -5,7 +5,7 @@ use lazy_regex::regex;
 use crate::config::NamingScheme;
 use crate::parsing::Node;
 use crate::processing::{NodeData, NodeType, apply_naming_scheme, build_plugin_source,
-                        flatten_root_node};
+                        flatten_root_node, unclosed_delimiter};
 use crate::testing::*;
 
 #[rstest]
@@ -122,3 +122,14 @@ fn test_build_plugin_source(
         ))
         .is_equal_to(expected);
 }
+
+#[rstest]
+#[case::closed("A { B [ C ] }", None)]
+#[case::enumeration("A { B", Some(2))]
+#[case::innermost("A { B [ C", Some(6))]
+#[case::after_closed("A [ B ]\nC { D", Some(10))]
+#[case::commented("A { B // }", Some(2))]
+#[case::in_comment("// A {\nB", None)]
+fn test_unclosed_delimiter(#[case] states: &str, #[case] expected: Option<usize>) {
+    assert_that!(unclosed_delimiter(states)).is_equal_to(expected);
+}
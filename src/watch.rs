@@ -0,0 +1,189 @@
+//! Regenerate on every change of a state definition, available with the `watch` feature
+
+#[cfg(test)]
+mod tests;
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, channel};
+use std::time::{Duration, Instant};
+use std::{fs, io};
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::generate::core::content_hash;
+use crate::generate::generator::Generator;
+use crate::generate::{GeneratorError, render_template_file};
+use crate::prelude::{GenerationOutcome, PluginConfig};
+
+/// How long to wait for more changes before regenerating
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// The file to watch and what to generate from it
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WatchTarget {
+    /// Generate `dst` from the states in `src`,
+    /// like [`generate_plugin`](crate::prelude::generate_plugin)
+    Plugin {
+        /// The state definition
+        src: PathBuf,
+        /// The generated Rust file
+        dst: PathBuf,
+    },
+    /// Update a template in place, like [`update_template`](crate::prelude::update_template)
+    Template(PathBuf),
+}
+
+impl WatchTarget {
+    /// The file whose changes trigger regeneration
+    pub fn source(&self) -> &Path {
+        match self {
+            Self::Plugin { src, .. } => src,
+            Self::Template(path) => path,
+        }
+    }
+}
+
+/// Watches the source of a [`WatchTarget`] and regenerates when it changes
+/// ```rust no_run
+/// use bevy_state_plugin_generator::prelude::*;
+/// use bevy_state_plugin_generator::watch::{StateWatcher, WatchTarget};
+/// let target = WatchTarget::Template("src/states.rs".into());
+/// StateWatcher::new(target, PluginConfig::default())
+///     .expect("Failed to watch")
+///     .run(|result| match result {
+///         Ok(outcome) => println!("changed={} {}", outcome.changed, outcome.path.display()),
+///         Err(error) => eprintln!("error: {error}"),
+///     });
+/// ```
+pub struct StateWatcher {
+    target: WatchTarget,
+    config: PluginConfig,
+    debounce: Duration,
+    events: Receiver<notify::Result<Event>>,
+    /// The hash of the template as it was last written by the watcher itself,
+    /// so that writing it does not count as a change
+    written: RefCell<Option<String>>,
+    // dropping the watcher stops the notifications
+    _watcher: RecommendedWatcher,
+}
+
+impl StateWatcher {
+    /// Start watching the source of `target`
+    pub fn new(target: WatchTarget, config: PluginConfig) -> Result<Self, GeneratorError> {
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(io::Error::other)?;
+        // editors often replace files instead of writing them, so watch the directory
+        let source = fs::canonicalize(target.source())?;
+        let directory = source.parent().unwrap_or(&source);
+        watcher
+            .watch(directory, RecursiveMode::NonRecursive)
+            .map_err(io::Error::other)?;
+        Ok(Self {
+            target,
+            config,
+            debounce: DEFAULT_DEBOUNCE,
+            events,
+            written: RefCell::new(None),
+            _watcher: watcher,
+        })
+    }
+
+    /// Set how long to wait for more changes before regenerating
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Regenerate the output from the current source
+    pub fn regenerate(&self) -> Result<GenerationOutcome, GeneratorError> {
+        let config = self.config.clone();
+        match &self.target {
+            WatchTarget::Plugin { src, dst } => Generator::new()
+                .cargo(false)
+                .source(src)
+                .output(dst, config)
+                .run_single(),
+            WatchTarget::Template(path) => {
+                let rendered = render_template_file(path, config)?;
+                let hash = content_hash(&rendered.source);
+                let outcome = rendered.write(path, self.config.force)?;
+                self.written.replace(Some(hash));
+                Ok(outcome)
+            }
+        }
+    }
+
+    /// Block until the source changes, then regenerate.
+    /// Returns `None` once no more changes can be observed.
+    pub fn next_change(&self) -> Option<Result<GenerationOutcome, GeneratorError>> {
+        self.next_change_until(None)
+    }
+
+    /// Like [`StateWatcher::next_change`], but also returns `None` when the
+    /// source did not change before `deadline`
+    pub(crate) fn next_change_until(
+        &self,
+        deadline: Option<Instant>,
+    ) -> Option<Result<GenerationOutcome, GeneratorError>> {
+        loop {
+            loop {
+                let event = match deadline {
+                    Some(deadline) => {
+                        let timeout = deadline.saturating_duration_since(Instant::now());
+                        self.events.recv_timeout(timeout).ok()?
+                    }
+                    None => self.events.recv().ok()?,
+                };
+                if self.concerns_source(event) {
+                    break;
+                }
+            }
+            // wait until the changes settle down
+            loop {
+                match self.events.recv_timeout(self.debounce) {
+                    Ok(_) => continue,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return None,
+                }
+            }
+            if !self.is_own_write() {
+                return Some(self.regenerate());
+            }
+        }
+    }
+
+    /// Regenerate once, then again on every change, reporting each result.
+    /// Errors are reported and do not stop the watcher.
+    pub fn run(self, mut report: impl FnMut(Result<GenerationOutcome, GeneratorError>)) {
+        report(self.regenerate());
+        while let Some(result) = self.next_change() {
+            report(result);
+        }
+    }
+
+    /// Whether the source is still what the watcher wrote to it last
+    fn is_own_write(&self) -> bool {
+        let written = self.written.borrow();
+        written.as_ref().is_some_and(|written| {
+            fs::read_to_string(self.target.source())
+                .is_ok_and(|source| content_hash(&source) == *written)
+        })
+    }
+
+    fn concerns_source(&self, event: notify::Result<Event>) -> bool {
+        let Ok(event) = event else {
+            return false;
+        };
+        let Ok(source) = fs::canonicalize(self.target.source()) else {
+            // the source was removed or renamed, which is a change as well
+            return true;
+        };
+        let is_modification = event.kind.is_create() || event.kind.is_modify();
+        is_modification
+            && event
+                .paths
+                .iter()
+                .any(|path| fs::canonicalize(path).is_ok_and(|path| path == source))
+    }
+}
@@ -0,0 +1,86 @@
+use std::fs;
+use std::time::{Duration, Instant};
+
+use rstest::rstest;
+use speculoos::prelude::*;
+use tempfile::TempDir;
+
+use crate::prelude::PluginConfig;
+use crate::watch::{StateWatcher, WatchTarget};
+
+/// Give up waiting for a change after this long
+const PATIENCE: Duration = Duration::from_millis(500);
+
+#[rstest]
+#[timeout(Duration::from_secs(5))]
+fn test_watch_plugin_regenerates_on_change() {
+    let dir = TempDir::new().unwrap();
+    let src = dir.path().join("states.txt");
+    let dst = dir.path().join("states.rs");
+    fs::write(&src, "Loading").unwrap();
+    let target = WatchTarget::Plugin {
+        src: src.clone(),
+        dst: dst.clone(),
+    };
+    let watcher = StateWatcher::new(target, PluginConfig::default())
+        .unwrap()
+        .with_debounce(Duration::from_millis(20));
+
+    assert_that!(watcher.regenerate().unwrap().changed).is_true();
+    fs::write(src, "Loading Ready").unwrap();
+    let deadline = Some(Instant::now() + PATIENCE);
+    let outcome = watcher.next_change_until(deadline).unwrap().unwrap();
+    assert_that!(outcome.changed).is_true();
+    assert_that!(fs::read_to_string(dst).unwrap().as_str()).contains("GameStateReady");
+}
+
+#[rstest]
+#[timeout(Duration::from_secs(5))]
+fn test_watch_reports_errors_and_keeps_going() {
+    let dir = TempDir::new().unwrap();
+    let template = dir.path().join("states.rs");
+    fs::write(&template, "// bspg:\n// Loading\n").unwrap();
+    let watcher = StateWatcher::new(
+        WatchTarget::Template(template.clone()),
+        PluginConfig::default(),
+    )
+    .unwrap()
+    .with_debounce(Duration::from_millis(20));
+
+    fs::write(&template, "// bspg:\n// Loading {\n").unwrap();
+    let deadline = Some(Instant::now() + PATIENCE);
+    assert_that!(watcher.next_change_until(deadline).unwrap()).is_err();
+    fs::write(&template, "// bspg:\n// Loading { Assets }\n").unwrap();
+    let deadline = Some(Instant::now() + PATIENCE);
+    assert_that!(watcher.next_change_until(deadline).unwrap()).is_ok();
+    assert_that!(fs::read_to_string(template).unwrap().as_str()).contains("GameStateLoadingAssets");
+}
+
+#[rstest]
+#[timeout(Duration::from_secs(5))]
+fn test_watch_template_ignores_its_own_writes() {
+    let dir = TempDir::new().unwrap();
+    let template = dir.path().join("states.rs");
+    fs::write(&template, "// bspg:\n// Loading\n").unwrap();
+    let watcher = StateWatcher::new(
+        WatchTarget::Template(template.clone()),
+        PluginConfig::default(),
+    )
+    .unwrap()
+    .with_debounce(Duration::from_millis(20));
+
+    assert_that!(watcher.regenerate().unwrap().changed).is_true();
+    assert_that!(watcher.next_change_until(Some(Instant::now() + PATIENCE))).is_none();
+
+    fs::write(&template, "// bspg:\n// Loading Ready\n").unwrap();
+    let deadline = Some(Instant::now() + PATIENCE);
+    assert_that!(
+        watcher
+            .next_change_until(deadline)
+            .unwrap()
+            .unwrap()
+            .changed
+    )
+    .is_true();
+    assert_that!(watcher.next_change_until(Some(Instant::now() + PATIENCE))).is_none();
+}
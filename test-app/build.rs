@@ -1,17 +1,18 @@
 use bevy_state_plugin_generator::prelude::*;
 
 fn main() {
-    let plugin_config = PluginConfig {
-        naming_scheme: NamingScheme::Short,
-        ..Default::default()
-    };
+    let plugin_config = PluginConfig::default().with_naming_scheme(NamingScheme::Short);
 
     update_template("src/auto_generated_states.rs", plugin_config)
         .expect("Failed to update template");
 
+    let mut generator = Generator::new().source("src/states.txt");
     for naming_scheme in [NamingScheme::None, NamingScheme::Short, NamingScheme::Full] {
         let output_path = format!("src/generated_states_{}.rs", naming_scheme.tag());
-        generate_plugin("src/states.txt", output_path, naming_scheme.into())
-            .expect("Failed to generate plugin: {output_path}");
+        generator = generator.output(output_path, naming_scheme.into());
     }
+    generator.run().expect("Failed to generate plugins");
+
+    generate_plugin_to_out_dir("src/states.txt", "generated_states.rs", Default::default())
+        .expect("Failed to generate plugin into OUT_DIR");
 }
@@ -1,5 +1,6 @@
 // generated by bevy_state_plugin_generator v1.4.4
-// WARN: unknown setting: 'nonsense'
+// hash: 20f9025daac4195f
+// WARN: line 4: unknown setting: 'nonsense'
 // bspg:nonsense 123
 // bspg:plugin_name MyCustomStatesPlugin
 // bspg:root_state_name RootState
@@ -28,7 +29,6 @@ pub mod lmao {
         Ready,
         Exiting,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(RootState = RootState::Loading)]
     pub enum RootStateLoading {
@@ -36,8 +36,7 @@ pub mod lmao {
         Configs,
         Assets,
     }
-
-    // when the game-assets have loaded
+    /// when the game-assets have loaded
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(RootState = RootState::Ready)]
     pub enum RootStateReady {
@@ -45,23 +44,18 @@ pub mod lmao {
         Playing,
         Paused,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(RootState = RootState::Exiting)]
     pub struct RootStateExiting;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(RootStateLoading = RootStateLoading::Configs)]
     pub struct RootStateLoadingConfigs;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(RootStateLoading = RootStateLoading::Assets)]
     pub struct RootStateLoadingAssets;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(RootStateReady = RootStateReady::Playing)]
     pub struct RootStateReadyPlaying;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(RootStateReady = RootStateReady::Paused)]
     pub struct RootStateReadyPaused;
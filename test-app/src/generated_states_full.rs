@@ -1,5 +1,6 @@
 // generated by bevy_state_plugin_generator v1.4.4
 // src: src/states.txt
+// hash: 132abe1b07340015
 // Game {
 //     Loading {
 //         // configuration, including which assets to load
@@ -27,7 +28,6 @@ pub mod states {
         #[default]
         Game,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameState = GameState::Game)]
     pub enum GameStateGame {
@@ -36,7 +36,6 @@ pub mod states {
         Ready,
         Exiting,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGame = GameStateGame::Loading)]
     pub enum GameStateGameLoading {
@@ -44,7 +43,6 @@ pub mod states {
         Configuration,
         Assets,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGame = GameStateGame::Ready)]
     pub enum GameStateGameReady {
@@ -52,29 +50,23 @@ pub mod states {
         Paused,
         Playing,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGame = GameStateGame::Exiting)]
     pub struct GameStateGameExiting;
-
-    // configuration, including which assets to load
+    /// configuration, including which assets to load
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGameLoading = GameStateGameLoading::Configuration)]
     pub struct GameStateGameLoadingConfiguration;
-
-    // load actual assets; images, sounds, etc.
+    /// load actual assets; images, sounds, etc.
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGameLoading = GameStateGameLoading::Assets)]
     pub struct GameStateGameLoadingAssets;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGameReady = GameStateGameReady::Paused)]
     pub struct GameStateGameReadyPaused;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGameReady = GameStateGameReady::Playing)]
     pub struct GameStateGameReadyPlaying;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGameReadyPlaying = GameStateGameReadyPlaying)]
     pub enum GameStateGameReadyPlayingPlayer {
@@ -83,7 +75,6 @@ pub mod states {
         Dead,
         Invincible,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGameReadyPlaying = GameStateGameReadyPlaying)]
     pub enum GameStateGameReadyPlayingEnemies {
@@ -92,7 +83,6 @@ pub mod states {
         Aggressive,
         Defensive,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGameReadyPlaying = GameStateGameReadyPlaying)]
     pub enum GameStateGameReadyPlayingWeather {
@@ -103,47 +93,44 @@ pub mod states {
         Hot,
         Cold,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGameReadyPlayingPlayer = GameStateGameReadyPlayingPlayer::Alive)]
     pub struct GameStateGameReadyPlayingPlayerAlive;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGameReadyPlayingPlayer = GameStateGameReadyPlayingPlayer::Dead)]
     pub struct GameStateGameReadyPlayingPlayerDead;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
-    #[source(GameStateGameReadyPlayingPlayer = GameStateGameReadyPlayingPlayer::Invincible)]
+    #[source(
+        GameStateGameReadyPlayingPlayer = GameStateGameReadyPlayingPlayer::Invincible
+    )]
     pub struct GameStateGameReadyPlayingPlayerInvincible;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
-    #[source(GameStateGameReadyPlayingEnemies = GameStateGameReadyPlayingEnemies::Passive)]
+    #[source(
+        GameStateGameReadyPlayingEnemies = GameStateGameReadyPlayingEnemies::Passive
+    )]
     pub struct GameStateGameReadyPlayingEnemiesPassive;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
-    #[source(GameStateGameReadyPlayingEnemies = GameStateGameReadyPlayingEnemies::Aggressive)]
+    #[source(
+        GameStateGameReadyPlayingEnemies = GameStateGameReadyPlayingEnemies::Aggressive
+    )]
     pub struct GameStateGameReadyPlayingEnemiesAggressive;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
-    #[source(GameStateGameReadyPlayingEnemies = GameStateGameReadyPlayingEnemies::Defensive)]
+    #[source(
+        GameStateGameReadyPlayingEnemies = GameStateGameReadyPlayingEnemies::Defensive
+    )]
     pub struct GameStateGameReadyPlayingEnemiesDefensive;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGameReadyPlayingWeather = GameStateGameReadyPlayingWeather::Nice)]
     pub struct GameStateGameReadyPlayingWeatherNice;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGameReadyPlayingWeather = GameStateGameReadyPlayingWeather::Mist)]
     pub struct GameStateGameReadyPlayingWeatherMist;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGameReadyPlayingWeather = GameStateGameReadyPlayingWeather::Rain)]
     pub struct GameStateGameReadyPlayingWeatherRain;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGameReadyPlayingWeather = GameStateGameReadyPlayingWeather::Hot)]
     pub struct GameStateGameReadyPlayingWeatherHot;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGameReadyPlayingWeather = GameStateGameReadyPlayingWeather::Cold)]
     pub struct GameStateGameReadyPlayingWeatherCold;
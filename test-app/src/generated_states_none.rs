@@ -1,5 +1,6 @@
 // generated by bevy_state_plugin_generator v1.4.4
 // src: src/states.txt
+// hash: 894d8dccc64f49a9
 // Game {
 //     Loading {
 //         // configuration, including which assets to load
@@ -27,7 +28,6 @@ pub mod states {
         #[default]
         Game,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameState = GameState::Game)]
     pub enum Game {
@@ -36,7 +36,6 @@ pub mod states {
         Ready,
         Exiting,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Game = Game::Loading)]
     pub enum Loading {
@@ -44,7 +43,6 @@ pub mod states {
         Configuration,
         Assets,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Game = Game::Ready)]
     pub enum Ready {
@@ -52,29 +50,23 @@ pub mod states {
         Paused,
         Playing,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Game = Game::Exiting)]
     pub struct Exiting;
-
-    // configuration, including which assets to load
+    /// configuration, including which assets to load
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Loading = Loading::Configuration)]
     pub struct Configuration;
-
-    // load actual assets; images, sounds, etc.
+    /// load actual assets; images, sounds, etc.
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Loading = Loading::Assets)]
     pub struct Assets;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Ready = Ready::Paused)]
     pub struct Paused;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Ready = Ready::Playing)]
     pub struct Playing;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Playing = Playing)]
     pub enum Player {
@@ -83,7 +75,6 @@ pub mod states {
         Dead,
         Invincible,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Playing = Playing)]
     pub enum Enemies {
@@ -92,7 +83,6 @@ pub mod states {
         Aggressive,
         Defensive,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Playing = Playing)]
     pub enum Weather {
@@ -103,47 +93,36 @@ pub mod states {
         Hot,
         Cold,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Player = Player::Alive)]
     pub struct Alive;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Player = Player::Dead)]
     pub struct Dead;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Player = Player::Invincible)]
     pub struct Invincible;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Enemies = Enemies::Passive)]
     pub struct Passive;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Enemies = Enemies::Aggressive)]
     pub struct Aggressive;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Enemies = Enemies::Defensive)]
     pub struct Defensive;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Weather = Weather::Nice)]
     pub struct Nice;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Weather = Weather::Mist)]
     pub struct Mist;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Weather = Weather::Rain)]
     pub struct Rain;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Weather = Weather::Hot)]
     pub struct Hot;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(Weather = Weather::Cold)]
     pub struct Cold;
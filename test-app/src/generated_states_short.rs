@@ -1,5 +1,6 @@
 // generated by bevy_state_plugin_generator v1.4.4
 // src: src/states.txt
+// hash: 463ace7edfd44449
 // Game {
 //     Loading {
 //         // configuration, including which assets to load
@@ -27,7 +28,6 @@ pub mod states {
         #[default]
         Game,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameState = GameState::Game)]
     pub enum GameStateGame {
@@ -36,7 +36,6 @@ pub mod states {
         Ready,
         Exiting,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGame = GameStateGame::Loading)]
     pub enum GameLoading {
@@ -44,7 +43,6 @@ pub mod states {
         Configuration,
         Assets,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGame = GameStateGame::Ready)]
     pub enum GameReady {
@@ -52,29 +50,23 @@ pub mod states {
         Paused,
         Playing,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameStateGame = GameStateGame::Exiting)]
     pub struct GameExiting;
-
-    // configuration, including which assets to load
+    /// configuration, including which assets to load
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameLoading = GameLoading::Configuration)]
     pub struct LoadingConfiguration;
-
-    // load actual assets; images, sounds, etc.
+    /// load actual assets; images, sounds, etc.
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameLoading = GameLoading::Assets)]
     pub struct LoadingAssets;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameReady = GameReady::Paused)]
     pub struct ReadyPaused;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(GameReady = GameReady::Playing)]
     pub struct ReadyPlaying;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(ReadyPlaying = ReadyPlaying)]
     pub enum PlayingPlayer {
@@ -83,7 +75,6 @@ pub mod states {
         Dead,
         Invincible,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(ReadyPlaying = ReadyPlaying)]
     pub enum PlayingEnemies {
@@ -92,7 +83,6 @@ pub mod states {
         Aggressive,
         Defensive,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(ReadyPlaying = ReadyPlaying)]
     pub enum PlayingWeather {
@@ -103,47 +93,36 @@ pub mod states {
         Hot,
         Cold,
     }
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(PlayingPlayer = PlayingPlayer::Alive)]
     pub struct PlayerAlive;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(PlayingPlayer = PlayingPlayer::Dead)]
     pub struct PlayerDead;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(PlayingPlayer = PlayingPlayer::Invincible)]
     pub struct PlayerInvincible;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(PlayingEnemies = PlayingEnemies::Passive)]
     pub struct EnemiesPassive;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(PlayingEnemies = PlayingEnemies::Aggressive)]
     pub struct EnemiesAggressive;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(PlayingEnemies = PlayingEnemies::Defensive)]
     pub struct EnemiesDefensive;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(PlayingWeather = PlayingWeather::Nice)]
     pub struct WeatherNice;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(PlayingWeather = PlayingWeather::Mist)]
     pub struct WeatherMist;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(PlayingWeather = PlayingWeather::Rain)]
     pub struct WeatherRain;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(PlayingWeather = PlayingWeather::Hot)]
     pub struct WeatherHot;
-
     #[derive(bevy::prelude::SubStates, Hash, Default, Debug, Clone, PartialEq, Eq)]
     #[source(PlayingWeather = PlayingWeather::Cold)]
     pub struct WeatherCold;
@@ -3,6 +3,18 @@ cfg_if::cfg_if! {
         #[allow(dead_code)]
         mod auto_generated_states;
         use auto_generated_states::MyCustomStatesPlugin as GeneratedStatesPlugin;
+    } else if #[cfg(feature = "macro")] {
+        #[allow(dead_code)]
+        mod macro_states {
+            bevy_state_plugin_generator_macros::include_states!("src/states.txt");
+        }
+        use macro_states::GeneratedStatesPlugin;
+    } else if #[cfg(feature = "out_dir")] {
+        #[allow(dead_code)]
+        mod out_dir_states {
+            bevy_state_plugin_generator_macros::include_generated_states!("generated_states.rs");
+        }
+        use out_dir_states::GeneratedStatesPlugin;
     } else if #[cfg(feature = "full")] {
         #[allow(dead_code)]
         mod generated_states_full;